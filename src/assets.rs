@@ -1,99 +1,71 @@
-use crate::utils::is_windows;
+use crate::{
+    audio::{AudioBackend, Mixer, SoundLibrary, AUDIO_SETTINGS_PATH},
+    manifest::{asset_files, file_name, AssetManifest, TextureId},
+    player::PlayerStance,
+};
 use image::{DynamicImage, GenericImageView};
 use macroquad::prelude::*;
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
-use std::{
-    collections::HashMap,
-    io::{BufReader, Cursor},
-};
+use std::{collections::HashMap, path::PathBuf};
+
+pub const PLAYER_IDLE_BITMAP: &str = "assets/Bitmaps/player_idle_bitmap.png";
+pub const PLAYER_AIMING_BITMAP: &str = "assets/Bitmaps/player_aiming_bitmap.png";
 
 pub struct Assets {
     textures: HashMap<String, Texture2D>,
-    sounds: HashMap<String, Vec<u8>>,
-    audio_handle: OutputStreamHandle,
-    _audio_stream: OutputStream,
+    pub sounds: SoundLibrary,
 }
 
 impl Assets {
-    pub fn get_texture(&self, texture: &str) -> Texture2D {
-        if let Some(texture) = self.textures.get(texture) {
+    pub fn get_texture(&self, texture: TextureId) -> Texture2D {
+        if let Some(texture) = self.textures.get(&texture.file_name()) {
             texture.to_owned()
         } else {
             error_texture()
         }
     }
 
-    pub fn play_sound(&self, sound_name: &str) {
-        if let Some(sound) = self.sounds.get(sound_name) {
-            let sound = sound.clone(); // clone the data if needed
-            let sink = Sink::try_new(&self.audio_handle).unwrap();
-            let cursor = Cursor::new(sound);
-
-            if let Ok(source) = Decoder::new(BufReader::new(cursor)) {
-                sink.append(source);
-                sink.detach();
-            } else {
-                println!("Failed to decode sound");
-            }
-        } else {
-            println!("Sound '{}' not found.", sound_name);
-        }
-    }
-
     async fn load_texture(path: &str) -> Result<Texture2D, macroquad::Error> {
         let texture = Texture2D::from_image(&load_image(path).await?);
         texture.set_filter(FilterMode::Nearest);
         Ok(texture)
     }
 
-    // Stack based search through assets folder, and loads in all assets
-    pub async fn load_all_assets() -> Self {
+    // Loads every image and sound in the assets folder, keyed by file name
+    pub async fn load_all_assets(files: &[PathBuf], audio: AudioBackend) -> Self {
         let mut textures: HashMap<String, Texture2D> = HashMap::new();
-        let mut sounds: HashMap<String, Vec<u8>> = HashMap::new();
-        let mut dirs_to_explore = vec![std::path::PathBuf::from("assets")];
+        let mut sounds = SoundLibrary::new(Mixer::new(audio));
 
-        while let Some(dir) = dirs_to_explore.pop() {
-            for entry in std::fs::read_dir(dir).unwrap() {
-                let entry = entry.unwrap();
-                let path = entry.path();
-                if path.is_dir() && path.to_str().unwrap() != "temp" {
-                    dirs_to_explore.push(path);
-                } else if path.is_file() {
-                    let path_str = path.to_string_lossy().to_string();
-                    // Images
-                    if path_str.ends_with(".png") {
-                        let key_path_str = match is_windows() {
-                            true => path_str.split('\\').last().unwrap(),
-                            false => path_str.split('/').last().unwrap(),
-                        };
-                        textures.insert(
-                            key_path_str.to_string(),
-                            Assets::load_texture(&path_str).await.unwrap(),
-                        );
-                    }
-                    // Sounds
-                    if path_str.ends_with(".wav") {
-                        let key_path_str = match is_windows() {
-                            true => path_str.split('\\').last().unwrap(),
-                            false => path_str.split('/').last().unwrap(),
-                        };
-                        let sound_bytes: Vec<u8> = load_file(&path_str).await.unwrap();
-                        sounds.insert(key_path_str.to_string(), sound_bytes);
-                    }
+        for path in files {
+            let path_str = path.to_string_lossy().to_string();
+            // Images
+            if path_str.ends_with(".png") {
+                textures.insert(
+                    file_name(path),
+                    Assets::load_texture(&path_str).await.unwrap(),
+                );
+            }
+            // Sounds
+            if path_str.ends_with(".wav") {
+                let sound_bytes: Vec<u8> = load_file(&path_str).await.unwrap();
+                if sounds.insert(file_name(path), sound_bytes).is_err() {
+                    println!("Failed to decode sound '{path_str}'");
                 }
             }
         }
-        let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-        Assets {
-            textures,
-            sounds,
-            _audio_stream: _stream,
-            audio_handle: stream_handle,
+        if let Err(err) = sounds.mixer.load_settings(AUDIO_SETTINGS_PATH) {
+            println!("Invalid audio settings '{AUDIO_SETTINGS_PATH}': {err}");
         }
+        Assets { textures, sounds }
     }
 
-    pub async fn new() -> Self {
-        let mut assets = Self::load_all_assets().await;
+    // Missing assets are reported before loading, unused ones only by the validate flag
+    pub async fn new(audio: AudioBackend) -> Self {
+        let files = asset_files();
+        let report = AssetManifest::expected().validate(&files);
+        if report.has_errors() {
+            report.print();
+        }
+        let mut assets = Self::load_all_assets(&files, audio).await;
         assets.load_clothes().await;
         assets
     }
@@ -136,35 +108,44 @@ impl Assets {
         image::load_from_memory(&{ load_file(path).await.unwrap() }).unwrap()
     }
 
+    // Inserts the idle and aiming textures of the clothes for every stance
+    // Lower stances are drawn darker to tell them apart at a glance
     async fn insert_clothes_pair(
         &mut self,
         bitmap: &DynamicImage,
         aiming_bitmap: &DynamicImage,
-        color_name: &str,
+        color_name: &'static str,
         colors: (Color, Color, Color, Color, Color),
     ) {
-        self.textures.insert(
-            format!("{color_name}_clothes_idle.png"),
-            Assets::get_clothes_from_bitmap(
-                bitmap,
-                (colors.0, colors.1, colors.2, colors.3, colors.4),
-            )
-            .await,
-        );
-        self.textures.insert(
-            format!("{color_name}_clothes_aiming.png"),
-            Assets::get_clothes_from_bitmap(
-                aiming_bitmap,
-                (colors.0, colors.1, colors.2, colors.3, colors.4),
-            )
-            .await,
-        );
+        for stance in PlayerStance::ALL {
+            let shade = |color: Color| {
+                let shade = stance.shade();
+                Color::new(color.r * shade, color.g * shade, color.b * shade, color.a)
+            };
+            let colors = (
+                shade(colors.0),
+                shade(colors.1),
+                shade(colors.2),
+                shade(colors.3),
+                shade(colors.4),
+            );
+            for (aiming, bitmap) in [(false, bitmap), (true, aiming_bitmap)] {
+                let texture = TextureId::Clothes {
+                    color: color_name,
+                    stance,
+                    aiming,
+                };
+                self.textures.insert(
+                    texture.file_name(),
+                    Assets::get_clothes_from_bitmap(bitmap, colors).await,
+                );
+            }
+        }
     }
 
     pub async fn load_clothes(&mut self) {
-        let player_bitmap = Assets::load_bitmap("assets/Bitmaps/player_idle_bitmap.png").await;
-        let player_aiming_bitmap =
-            Assets::load_bitmap("assets/Bitmaps/player_aiming_bitmap.png").await;
+        let player_bitmap = Assets::load_bitmap(PLAYER_IDLE_BITMAP).await;
+        let player_aiming_bitmap = Assets::load_bitmap(PLAYER_AIMING_BITMAP).await;
 
         self.insert_clothes_pair(
             &player_bitmap,
@@ -205,6 +186,32 @@ impl Assets {
             ),
         )
         .await;
+        self.insert_clothes_pair(
+            &player_bitmap,
+            &player_aiming_bitmap,
+            "light_armor",
+            (
+                Color::from_rgba(107, 112, 74, 255),
+                Color::from_rgba(78, 82, 54, 255),
+                Color::from_rgba(96, 101, 66, 255),
+                Color::from_rgba(48, 50, 36, 255),
+                Color::from_rgba(33, 35, 25, 255),
+            ),
+        )
+        .await;
+        self.insert_clothes_pair(
+            &player_bitmap,
+            &player_aiming_bitmap,
+            "heavy_armor",
+            (
+                Color::from_rgba(96, 100, 106, 255),
+                Color::from_rgba(66, 69, 74, 255),
+                Color::from_rgba(84, 88, 94, 255),
+                Color::from_rgba(40, 42, 46, 255),
+                Color::from_rgba(27, 28, 31, 255),
+            ),
+        )
+        .await;
     }
 }
 
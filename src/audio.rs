@@ -0,0 +1,514 @@
+use crate::{manifest::SoundId, player::Player, tile_map::TileMap};
+use macroquad::prelude::*;
+use rodio::{
+    decoder::DecoderError,
+    source::{Buffered, ChannelVolume},
+    Decoder, OutputStream, OutputStreamHandle, Sink, Source,
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufReader, Cursor},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+pub const AUDIO_SETTINGS_PATH: &str = "audio_settings.txt";
+pub const HEADLESS_AUDIO_FLAG: &str = "--headless-audio";
+
+// Sound kept in memory, decoded the first time it plays and shared by every clone after that
+pub type CachedSound = Buffered<Decoder<Cursor<Vec<u8>>>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bus {
+    Master, // Applies on top of every other bus
+    Sfx,
+    Music,
+    Ui,
+}
+
+impl Bus {
+    pub const ALL: [Bus; 4] = [Bus::Master, Bus::Sfx, Bus::Music, Bus::Ui];
+
+    pub fn from_name(name: &str) -> Option<Bus> {
+        Bus::ALL.into_iter().find(|bus| bus.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Bus::Master => "master",
+            Bus::Sfx => "sfx",
+            Bus::Music => "music",
+            Bus::Ui => "ui",
+        }
+    }
+}
+
+// Where sounds end up, picked once at startup
+pub enum AudioBackend {
+    Rodio {
+        audio_handle: OutputStreamHandle,
+        _audio_stream: OutputStream,
+    },
+    // Plays nothing and only keeps the names of what would have played
+    // Used on machines without a sound device, like servers and CI
+    Recording {
+        played: RefCell<VecDeque<String>>, // Oldest first
+    },
+}
+
+impl AudioBackend {
+    const RECORDING_LENGTH: usize = 256;
+
+    // Plays on the sound device unless headless, falling back to recording if there is none
+    pub fn new(headless: bool) -> AudioBackend {
+        if !headless {
+            match OutputStream::try_default() {
+                Ok((audio_stream, audio_handle)) => {
+                    return AudioBackend::Rodio {
+                        audio_handle,
+                        _audio_stream: audio_stream,
+                    }
+                }
+                Err(err) => println!("No sound device, playing without sound: {err}"),
+            }
+        }
+        AudioBackend::Recording {
+            played: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    // Headless if the flag was passed on the command line
+    pub fn from_args() -> AudioBackend {
+        AudioBackend::new(std::env::args().any(|arg| arg == HEADLESS_AUDIO_FLAG))
+    }
+
+    // Sink playing on the backend, the name is what's about to be played on it
+    fn sink(&self, name: &str) -> Option<Sink> {
+        match self {
+            AudioBackend::Rodio { audio_handle, .. } => Sink::try_new(audio_handle).ok(),
+            AudioBackend::Recording { played } => {
+                let mut played = played.borrow_mut();
+                if played.len() >= AudioBackend::RECORDING_LENGTH {
+                    played.pop_front();
+                }
+                played.push_back(name.to_string());
+                // Nothing reads from the sink, so sounds appended to it are never decoded
+                Some(Sink::new_idle().0)
+            }
+        }
+    }
+
+    // Names of the sounds played so far, always empty when playing on a sound device
+    pub fn played(&self) -> Vec<String> {
+        match self {
+            AudioBackend::Rodio { .. } => vec![],
+            AudioBackend::Recording { played } => played.borrow().iter().cloned().collect(),
+        }
+    }
+}
+
+// Plays sounds through volume buses, limiting how many copies of one sound play at once
+pub struct Mixer {
+    pub backend: AudioBackend,
+    volumes: [Arc<AtomicU32>; 4], // f32 bits, shared with playing sounds so changes are heard right away
+    voices: RefCell<HashMap<String, VecDeque<Sink>>>, // Oldest first
+}
+
+impl Mixer {
+    const LISTED_SOUNDS: usize = 6;
+    // How often playing sounds pick up changes to the bus volumes
+    const VOLUME_UPDATE_PERIOD: Duration = Duration::from_millis(20);
+
+    pub fn new(backend: AudioBackend) -> Self {
+        let mixer = Self {
+            backend,
+            volumes: Default::default(),
+            voices: RefCell::new(HashMap::new()),
+        };
+        for bus in Bus::ALL {
+            mixer.set_volume(bus, 1.0);
+        }
+        mixer
+    }
+
+    pub fn volume(&self, bus: Bus) -> f32 {
+        f32::from_bits(self.volumes[bus as usize].load(Ordering::Relaxed))
+    }
+
+    pub fn set_volume(&self, bus: Bus, volume: f32) {
+        self.volumes[bus as usize].store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    // Scales the source by the bus and master volume, following changes while it plays
+    fn on_bus<S>(&self, source: S, bus: Bus) -> impl Source<Item = f32> + Send
+    where
+        S: Source<Item = f32> + Send,
+    {
+        let master = self.volumes[Bus::Master as usize].clone();
+        let bus = self.volumes[bus as usize].clone();
+        let volume = move || {
+            f32::from_bits(master.load(Ordering::Relaxed))
+                * f32::from_bits(bus.load(Ordering::Relaxed))
+        };
+        source
+            .amplify(volume())
+            .periodic_access(Mixer::VOLUME_UPDATE_PERIOD, move |source| {
+                source.set_factor(volume())
+            })
+    }
+
+    // Plays the sound, stopping the oldest copy of it if too many are already playing
+    pub fn play<S>(&self, sound_name: &str, max_voices: usize, source: S, bus: Bus, volume: f32)
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        let Some(sink) = self.backend.sink(sound_name) else {
+            return;
+        };
+        sink.set_volume(volume);
+        sink.append(self.on_bus(source, bus));
+
+        let mut voices = self.voices.borrow_mut();
+        let voices = voices.entry(sound_name.to_string()).or_default();
+        voices.retain(|voice| !voice.empty());
+        while voices.len() >= max_voices {
+            voices.pop_front(); // Dropping the sink stops it
+        }
+        voices.push_back(sink);
+    }
+
+    // Plays the sound until the returned sink is dropped, without counting towards voice limits
+    pub fn play_looping<S>(
+        &self,
+        sound_name: &str,
+        source: S,
+        bus: Bus,
+        volume: f32,
+    ) -> Option<Sink>
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        let sink = self.backend.sink(sound_name)?;
+        sink.set_volume(volume);
+        sink.append(self.on_bus(source, bus));
+        Some(sink)
+    }
+
+    // Reads volumes saved as "<bus> <volume>" lines, a missing file keeps the defaults
+    pub fn load_settings(&self, path: &str) -> Result<(), String> {
+        let Ok(source) = std::fs::read_to_string(path) else {
+            return Ok(());
+        };
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |reason: &str| format!("line {}: {reason} ({line})", line_number + 1);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let bus = Bus::from_name(words[0]).ok_or_else(|| error("unknown bus"))?;
+            let volume: f32 = words
+                .get(1)
+                .ok_or_else(|| error("missing value"))?
+                .parse()
+                .map_err(|_| error("expected a number"))?;
+            self.set_volume(bus, volume);
+        }
+        Ok(())
+    }
+
+    pub fn save_settings(&self, path: &str) -> Result<(), String> {
+        let lines: Vec<String> = Bus::ALL
+            .iter()
+            .map(|bus| format!("{} {}", bus.name(), self.volume(*bus)))
+            .collect();
+        std::fs::write(path, lines.join("\n"))
+            .map_err(|err| format!("Failed to write audio settings '{path}': {err}"))
+    }
+
+    // Runs a console command, returning what to print back
+    // "sounds" lists the last sounds played when headless
+    // "volume" lists the volumes, "volume <bus> <0.0 to 1.0>" changes one and saves it
+    // Players change volumes from the audio settings screen, this is for debugging
+    pub fn run_command(&self, words: &[&str]) -> Result<String, String> {
+        if words[0] == "sounds" {
+            return match &self.backend {
+                AudioBackend::Rodio { .. } => Ok("Playing on the sound device".to_string()),
+                AudioBackend::Recording { .. } => {
+                    let played = self.backend.played();
+                    let last = &played[played.len().saturating_sub(Mixer::LISTED_SOUNDS)..];
+                    Ok(format!("Played: {}", last.join(", ")))
+                }
+            };
+        }
+        let Some(bus) = words.get(1) else {
+            let volumes: Vec<String> = Bus::ALL
+                .iter()
+                .map(|bus| format!("{} {:.2}", bus.name(), self.volume(*bus)))
+                .collect();
+            return Ok(volumes.join(", "));
+        };
+        let bus = Bus::from_name(bus).ok_or("unknown bus")?;
+        let volume: f32 = words
+            .get(2)
+            .ok_or("missing value")?
+            .parse()
+            .map_err(|_| "expected a number")?;
+        self.set_volume(bus, volume);
+        self.save_settings(AUDIO_SETTINGS_PATH)?;
+        Ok(format!(
+            "{} volume set to {:.2}",
+            bus.name(),
+            self.volume(bus)
+        ))
+    }
+}
+
+// Sounds loaded from the assets folder by file name, played through the mixer
+// Kept apart from the textures so sounds can be played without a window
+pub struct SoundLibrary {
+    sounds: HashMap<String, CachedSound>,
+    pub mixer: Mixer,
+}
+
+impl SoundLibrary {
+    pub fn new(mixer: Mixer) -> Self {
+        Self {
+            sounds: HashMap::new(),
+            mixer,
+        }
+    }
+
+    // Buffered so the sound is only decoded the first time it plays
+    pub fn insert(&mut self, sound_name: String, bytes: Vec<u8>) -> Result<(), DecoderError> {
+        let sound = Decoder::new(Cursor::new(bytes))?;
+        self.sounds.insert(sound_name, sound.buffered());
+        Ok(())
+    }
+
+    fn sound(&self, sound_name: &str) -> Option<CachedSound> {
+        let sound = self.sounds.get(sound_name).cloned();
+        if sound.is_none() {
+            println!("Sound '{}' not found.", sound_name);
+        }
+        sound
+    }
+
+    pub fn play(&self, sound: SoundId) {
+        self.play_on(sound, Bus::Sfx, 1.0);
+    }
+
+    pub fn play_on(&self, sound: SoundId, bus: Bus, volume: f32) {
+        let name = sound.file_name();
+        if let Some(source) = self.sound(&name) {
+            self.mixer.play(
+                &name,
+                sound.max_voices(),
+                source.convert_samples(),
+                bus,
+                volume,
+            );
+        }
+    }
+
+    // Plays a sound made at the position, quieter the further it is from the listener
+    // Passing the tile map muffles sounds behind walls
+    pub fn play_at(
+        &self,
+        sound: SoundId,
+        pos: Vec2,
+        hearing_distance: f32,
+        listener: &Listener,
+        tile_map: Option<&TileMap>,
+    ) {
+        let spatial = SpatialSound::new(pos, hearing_distance, listener, tile_map);
+        self.play_spatial(sound, &spatial);
+    }
+
+    // Plays the sound panned to the side it came from, muffled if walls are in the way
+    fn play_spatial(&self, sound: SoundId, spatial: &SpatialSound) {
+        if spatial.volume <= 0.0 {
+            return;
+        }
+        let name = sound.file_name();
+        let Some(source) = self.sound(&name) else {
+            return;
+        };
+        let source = source.convert_samples::<f32>();
+        let channel_volumes = spatial.channel_volumes();
+        match spatial.low_pass {
+            Some(cutoff) => self.mixer.play(
+                &name,
+                sound.max_voices(),
+                ChannelVolume::new(source.low_pass(cutoff), channel_volumes),
+                Bus::Sfx,
+                spatial.volume,
+            ),
+            None => self.mixer.play(
+                &name,
+                sound.max_voices(),
+                ChannelVolume::new(source, channel_volumes),
+                Bus::Sfx,
+                spatial.volume,
+            ),
+        }
+    }
+
+    // Plays the sound on repeat until the returned sink is dropped
+    pub fn play_looping(&self, sound: SoundId, volume: f32) -> Option<Sink> {
+        let name = sound.file_name();
+        let source = self.sound(&name)?;
+        self.mixer.play_looping(
+            &name,
+            source.repeat_infinite().convert_samples(),
+            Bus::Sfx,
+            volume,
+        )
+    }
+
+    // Streams a long track from disk on repeat instead of keeping it in memory
+    pub fn stream_looping(&self, path: &str, bus: Bus, volume: f32) -> Option<Sink> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                println!("Failed to open track '{path}': {err}");
+                return None;
+            }
+        };
+        match Decoder::new_looped(BufReader::new(file)) {
+            Ok(source) => self
+                .mixer
+                .play_looping(path, source.convert_samples(), bus, volume),
+            Err(_) => {
+                println!("Failed to decode track '{path}'");
+                None
+            }
+        }
+    }
+}
+
+// Where positional sounds are heard from
+#[derive(Clone, Copy)]
+pub struct Listener {
+    pub pos: Vec2,
+}
+
+impl Listener {
+    pub fn new(player: &Player) -> Self {
+        Self { pos: player.pos }
+    }
+}
+
+// How a sound made at some position sounds to the listener
+pub struct SpatialSound {
+    pub volume: f32,
+    pub pan: f32,              // -1.0 is fully left and 1.0 fully right
+    pub low_pass: Option<u32>, // Cutoff frequency when walls are in the way
+}
+
+impl SpatialSound {
+    // Sounds closer than this are only panned partly, so nearby sounds don't jump between ears
+    const FULL_PAN_DISTANCE: f32 = 8.0 * 12.0;
+    const MAX_PAN: f32 = 0.8;
+    // Every wall in the way makes the sound quieter and more muffled
+    const WALL_VOLUME: f32 = 0.6;
+    const MUFFLED_CUTOFF: f32 = 1600.0;
+
+    // Walls are only checked for if a tile map is given
+    pub fn new(
+        pos: Vec2,
+        hearing_distance: f32,
+        listener: &Listener,
+        tile_map: Option<&TileMap>,
+    ) -> SpatialSound {
+        let offset = pos - listener.pos;
+        let distance = offset.length();
+        let falloff = (1.0 - distance / hearing_distance).clamp(0.0, 1.0);
+
+        // The camera never turns, so the side of the screen the sound is on is the side it's heard on
+        let pan = match distance > 0.0 {
+            true => {
+                let closeness = (distance / SpatialSound::FULL_PAN_DISTANCE).min(1.0);
+                offset.x / distance * closeness * SpatialSound::MAX_PAN
+            }
+            false => 0.0,
+        };
+
+        let walls = tile_map.map_or(0, |tile_map| tile_map.walls_on_line(listener.pos, pos));
+        let low_pass = (walls > 0).then(|| (SpatialSound::MUFFLED_CUTOFF / walls as f32) as u32);
+
+        SpatialSound {
+            volume: falloff * falloff * SpatialSound::WALL_VOLUME.powi(walls as i32),
+            pan,
+            low_pass,
+        }
+    }
+
+    // Volume of the left and right channel
+    pub fn channel_volumes(&self) -> Vec<f32> {
+        vec![(1.0 - self.pan).min(1.0), (1.0 + self.pan).min(1.0)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::source::SineWave;
+
+    fn headless_sounds(names: &[&str]) -> SoundLibrary {
+        let mut sounds = SoundLibrary::new(Mixer::new(AudioBackend::new(true)));
+        for name in names {
+            let bytes = std::fs::read(format!("assets/sounds/{name}")).unwrap();
+            sounds.insert(name.to_string(), bytes).unwrap();
+        }
+        sounds
+    }
+
+    #[test]
+    fn headless_backend_records_played_sounds() {
+        let sounds = headless_sounds(&["sniper_shooting.wav", "ricochet.wav"]);
+        let listener = Listener { pos: Vec2::ZERO };
+
+        sounds.play(SoundId::GunShot("sniper"));
+        sounds.play_at(SoundId::Ricochet, vec2(10.0, 0.0), 100.0, &listener, None);
+        // Too far away to be heard, so it never plays
+        sounds.play_at(SoundId::Ricochet, vec2(500.0, 0.0), 100.0, &listener, None);
+
+        assert_eq!(
+            sounds.mixer.backend.played(),
+            ["sniper_shooting.wav", "ricochet.wav"]
+        );
+    }
+
+    #[test]
+    fn oldest_voice_is_stolen_past_the_limit() {
+        let mixer = Mixer::new(AudioBackend::new(true));
+        let max_voices = SoundId::Ricochet.max_voices();
+
+        // Every voice gets its own volume so they can be told apart
+        for index in 0..max_voices + 2 {
+            let volume = index as f32 / 10.0;
+            mixer.play(
+                "ricochet.wav",
+                max_voices,
+                SineWave::new(440.0),
+                Bus::Sfx,
+                volume,
+            );
+        }
+        mixer.play("door_open.wav", 8, SineWave::new(440.0), Bus::Sfx, 1.0);
+
+        let voices = mixer.voices.borrow();
+        let volumes: Vec<f32> = voices["ricochet.wav"].iter().map(Sink::volume).collect();
+        let newest: Vec<f32> = (2..max_voices + 2).map(|i| i as f32 / 10.0).collect();
+        assert_eq!(volumes, newest);
+        // Other sounds have voices of their own
+        assert_eq!(voices["door_open.wav"].len(), 1);
+        assert_eq!(mixer.backend.played().len(), max_voices + 3);
+    }
+}
@@ -8,6 +8,7 @@ pub struct GameCamera {
     pub target_zoom: f32,
     pub target: Vec2,
     pub offset: Vec2,
+    pub shake: f32,
     pub render_target: Option<RenderTarget>,
     pub viewport: Option<(i32, i32, i32, i32)>,
 }
@@ -76,6 +77,38 @@ impl GameCamera {
         const PAN_SPEED: f32 = 12.0; // Bigger number means slower pan
         let camera_dist_from_player = self.target - target;
         self.target -= camera_dist_from_player / PAN_SPEED * get_frame_time() * 60.0;
+        self.update_shake();
+    }
+
+    // Shake adds up from multiple sources but is capped so the screen stays readable
+    pub fn add_shake(&mut self, amount: f32) {
+        const MAX_SHAKE: f32 = 1.5;
+        self.shake = (self.shake + amount).min(MAX_SHAKE);
+    }
+
+    // Jitters the camera offset and lets the shake die down over time
+    fn update_shake(&mut self) {
+        const SHAKE_DECAY: f32 = 2.5; // Shake lost per second
+        const SHAKE_STRENGTH: f32 = 0.02; // Offset in screen space at full shake
+
+        self.shake = (self.shake - SHAKE_DECAY * get_frame_time()).max(0.0);
+        self.offset = vec2(rand::gen_range(-1.0, 1.0), rand::gen_range(-1.0, 1.0))
+            * self.shake.powi(2)
+            * SHAKE_STRENGTH;
+    }
+
+    // Same view as this camera, but drawing into the render target instead of the screen
+    pub fn with_render_target(&self, render_target: &RenderTarget) -> GameCamera {
+        GameCamera {
+            rotation: self.rotation,
+            zoom: self.zoom,
+            target_zoom: self.target_zoom,
+            target: self.target,
+            offset: self.offset,
+            shake: self.shake,
+            render_target: Some(render_target.clone()),
+            viewport: None,
+        }
     }
 
     pub fn get_visible_tiles(&self, world: &TileMap) -> Vec<(u16, u16)> {
@@ -131,6 +164,7 @@ impl Default for GameCamera {
         GameCamera {
             zoom: GameCamera::default_camera_zoom(),
             offset: vec2(0., 0.),
+            shake: 0.0,
             target: vec2(0., 0.),
             rotation: 0.,
             target_zoom: 15.0,
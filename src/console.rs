@@ -0,0 +1,93 @@
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+
+pub const CONSOLE_KEY: KeyCode = KeyCode::GraveAccent;
+
+// Text console for typing debug commands, only available in debug mode
+pub struct DebugConsole {
+    pub is_open: bool,
+    input: String,
+    log: VecDeque<String>,
+}
+
+impl DebugConsole {
+    const LOG_LENGTH: usize = 8;
+
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            input: String::new(),
+            log: VecDeque::new(),
+        }
+    }
+
+    // Returns the command entered this frame, if any
+    pub fn update(&mut self) -> Option<String> {
+        // Macroquad only empties its queue of typed characters when asked to
+        // Otherwise everything typed while playing would show up once the console opens
+        if is_key_pressed(CONSOLE_KEY) {
+            self.is_open = !self.is_open;
+            self.input.clear();
+            clear_input_queue();
+            return None;
+        }
+        if !self.is_open {
+            clear_input_queue();
+            return None;
+        }
+
+        // The queue hands out the newest character first
+        let typed: Vec<char> = std::iter::from_fn(get_char_pressed).collect();
+        for character in typed.into_iter().rev() {
+            if !character.is_control() && character != '`' {
+                self.input.push(character);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.input.pop();
+        }
+        if !is_key_pressed(KeyCode::Enter) || self.input.trim().is_empty() {
+            return None;
+        }
+        let command = std::mem::take(&mut self.input);
+        self.print(format!("> {command}"));
+        Some(command)
+    }
+
+    pub fn print(&mut self, line: String) {
+        self.log.push_back(line);
+        if self.log.len() > DebugConsole::LOG_LENGTH {
+            self.log.pop_front();
+        }
+    }
+
+    pub fn draw(&self) {
+        if !self.is_open {
+            return;
+        }
+        let text_size = 24.0;
+        let line_height = text_size * 1.1;
+        let height = line_height * (DebugConsole::LOG_LENGTH + 1) as f32 + 12.0;
+        let top = screen_height() - height;
+        draw_rectangle(
+            0.0,
+            top,
+            screen_width(),
+            height,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+
+        let first_line = DebugConsole::LOG_LENGTH - self.log.len();
+        for (index, line) in self.log.iter().enumerate() {
+            let y = top + line_height * (first_line + index + 1) as f32;
+            draw_text(line, 10.0, y, text_size, LIGHTGRAY);
+        }
+        draw_text(
+            &format!("> {}_", self.input),
+            10.0,
+            screen_height() - 10.0,
+            text_size,
+            WHITE,
+        );
+    }
+}
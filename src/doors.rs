@@ -0,0 +1,160 @@
+use crate::{
+    items::Item, manifest::SoundId, materials::TileMaterial, player::Player, tile_map::TileMap,
+};
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DoorState {
+    Closed,
+    Open,
+    Breached, // Blown open by an explosion, it can't be closed anymore
+}
+
+// Door filling a single tile, it blocks movement, bullets and sight while closed
+pub struct Door {
+    pub grid_x: u16,
+    pub grid_y: u16,
+    pub state: DoorState,
+    pub key: Option<Item::Key>, // Key needed to unlock the door, None if it never was locked
+    pub locked: bool,           // Cleared for good once the door is unlocked with its key
+    hinge: Vec2,                // Direction of the wall the door swings open against
+}
+
+impl Door {
+    pub const INTERACT_RANGE: f32 = 12.0;
+    // Explosions this close blow doors open
+    pub const BREACH_RADIUS: f32 = 8.0 * 2.5;
+    const THICKNESS: f32 = 1.5;
+
+    // Doors start closed, and block their tile right away
+    pub fn new(grid_x: u16, grid_y: u16, key: Option<Item::Key>, tile_map: &mut TileMap) -> Self {
+        let (hinge_x, hinge_y) = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .find(|&(x, y)| {
+                tile_map.is_collider(grid_x.wrapping_add_signed(x), grid_y.wrapping_add_signed(y))
+            })
+            .unwrap_or((-1, 0));
+        let door = Self {
+            grid_x,
+            grid_y,
+            state: DoorState::Closed,
+            locked: key.is_some(),
+            key,
+            hinge: Vec2::new(hinge_x as f32, hinge_y as f32),
+        };
+        tile_map.set_blocked(grid_x, grid_y, Some(door.material()));
+        door
+    }
+
+    // Locked doors are made of metal
+    pub fn material(&self) -> TileMaterial {
+        match self.key {
+            Some(_) => TileMaterial::Metal,
+            None => TileMaterial::Wood,
+        }
+    }
+
+    pub fn pos(&self) -> Vec2 {
+        Vec2::new(
+            self.grid_x as f32 * 8.0 + 4.0,
+            self.grid_y as f32 * 8.0 + 4.0,
+        )
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.grid_x as f32 * 8.0, self.grid_y as f32 * 8.0, 8.0, 8.0)
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state == DoorState::Closed && self.locked
+    }
+
+    fn player_has_key(&self, player: &Player) -> bool {
+        match &self.key {
+            Some(key) => player.inventory.count(&Item::Kind::Key(key.clone())) > 0,
+            None => false,
+        }
+    }
+
+    // Name of what interacting with the door would do
+    pub fn action_name(&self, player: &Player) -> &'static str {
+        match self.state {
+            DoorState::Open => "Close",
+            DoorState::Closed if !self.is_locked() => "Open",
+            DoorState::Closed if self.player_has_key(player) => "Unlock",
+            _ => "Locked",
+        }
+    }
+
+    // Opens or closes the door, unlocking it for good if the player carries its key
+    // Doors can't be closed on someone standing in the doorway
+    // Returns the sound the door makes, if any
+    pub fn interact(
+        &mut self,
+        player: &Player,
+        doorway_clear: bool,
+        tile_map: &mut TileMap,
+    ) -> Option<SoundId> {
+        match self.state {
+            DoorState::Breached => None,
+            DoorState::Open if !doorway_clear => None,
+            DoorState::Open => {
+                self.state = DoorState::Closed;
+                tile_map.set_blocked(self.grid_x, self.grid_y, Some(self.material()));
+                Some(SoundId::DoorClose)
+            }
+            DoorState::Closed if self.locked && !self.player_has_key(player) => {
+                Some(SoundId::DoorLocked)
+            }
+            DoorState::Closed => {
+                self.locked = false;
+                self.state = DoorState::Open;
+                tile_map.set_blocked(self.grid_x, self.grid_y, None);
+                Some(SoundId::DoorOpen)
+            }
+        }
+    }
+
+    pub fn breach(&mut self, tile_map: &mut TileMap) {
+        self.state = DoorState::Breached;
+        tile_map.set_blocked(self.grid_x, self.grid_y, None);
+    }
+
+    pub fn draw(&self) {
+        let color = match self.material() {
+            TileMaterial::Metal => Color::from_rgba(95, 105, 115, 255),
+            _ => Color::from_rgba(120, 82, 45, 255),
+        };
+        let tile = self.rect();
+        let rect = match self.state {
+            DoorState::Closed => tile,
+            // Open doors lie flat against the wall they're hinged on
+            DoorState::Open => match (self.hinge.x, self.hinge.y) {
+                (x, _) if x < 0.0 => Rect::new(tile.x, tile.y, Door::THICKNESS, tile.h),
+                (x, _) if x > 0.0 => Rect::new(
+                    tile.x + tile.w - Door::THICKNESS,
+                    tile.y,
+                    Door::THICKNESS,
+                    tile.h,
+                ),
+                (_, y) if y < 0.0 => Rect::new(tile.x, tile.y, tile.w, Door::THICKNESS),
+                _ => Rect::new(
+                    tile.x,
+                    tile.y + tile.h - Door::THICKNESS,
+                    tile.w,
+                    Door::THICKNESS,
+                ),
+            },
+            DoorState::Breached => return,
+        };
+        draw_rectangle(
+            rect.x + 0.5,
+            rect.y + 0.5,
+            rect.w,
+            rect.h,
+            Color::from_rgba(0, 0, 0, 70),
+        );
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 0.5, BLACK);
+    }
+}
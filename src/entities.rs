@@ -1,8 +1,20 @@
 use crate::{
     assets::Assets,
+    audio::{Listener, SoundLibrary},
     camera::GameCamera,
-    player::Player,
-    tile_map::{LineSegment, TileMap},
+    doors::{Door, DoorState},
+    explosion::Explosion,
+    inventory::ItemStack,
+    items::Item,
+    lighting::{Flash, Lamp, Light},
+    loot::Container,
+    manifest::{SoundId, TextureId},
+    materials::TileMaterial,
+    noise::{Noise, NoiseKind, NoiseSource},
+    player::{Player, PlayerStance, INTERACT_KEY},
+    status_effects::StatusEffectKind,
+    tile_map::{LineSegment, TileHit, TileMap},
+    utils::distance_to_segment,
 };
 use macroquad::prelude::*;
 use std::collections::HashSet;
@@ -12,8 +24,114 @@ pub struct Bullet {
     pub pos: Vec2,
     pub vel: f32,
     pub angle: f32,
+    pub damage: f32,
     pub collisions: Vec<Vec2>,
     pub last_pos: Vec2,
+    pub origin: Vec2,
+    pub has_suppressed: bool,
+    pub penetration: f32, // Penetration power left for passing through tiles
+    pub penetrated: Vec<(u16, u16)>, // Tiles the bullet already passed through
+}
+
+// What happened when a bullet reached a tile
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BulletImpact {
+    Stopped,
+    Penetrated,
+    Ricocheted,
+}
+
+impl Bullet {
+    // Bullets passing this close to a player suppress them
+    pub const SUPPRESSION_RADIUS: f32 = 10.0;
+    pub const SUPPRESSION_TIME: f32 = 2.5;
+
+    // Only hits closer than this to the surface, in radians, can ricochet
+    pub const MAX_RICOCHET_ANGLE: f32 = 0.45;
+    // Fraction of speed and damage kept after a ricochet
+    pub const RICOCHET_KEPT: f32 = 0.6;
+    // Past this distance bullet impacts can no longer be heard
+    pub const IMPACT_HEARING_DISTANCE: f32 = 8.0 * 25.0;
+
+    pub fn new(pos: Vec2, vel: f32, angle: f32, damage: f32, penetration: f32) -> Self {
+        Self {
+            pos,
+            vel,
+            angle,
+            damage,
+            collisions: vec![],
+            last_pos: pos,
+            origin: pos,
+            has_suppressed: false,
+            penetration,
+            penetrated: vec![],
+        }
+    }
+
+    pub fn direction(&self) -> Vec2 {
+        Vec2::new(self.angle.sin(), self.angle.cos())
+    }
+
+    // Glances off, passes through or stops at the tile it hit, depending on what it's made of
+    fn impact(&mut self, hit: &TileHit, material: TileMaterial) -> BulletImpact {
+        let direction = self.direction();
+        let is_shallow = direction.dot(hit.normal).abs() < Bullet::MAX_RICOCHET_ANGLE.sin();
+        if is_shallow && rand::gen_range(0.0, 1.0) < material.ricochet_chance() {
+            let reflected = direction - 2.0 * direction.dot(hit.normal) * hit.normal;
+            self.angle = f32::atan2(reflected.x, reflected.y);
+            self.vel *= Bullet::RICOCHET_KEPT;
+            self.damage *= Bullet::RICOCHET_KEPT;
+            self.last_pos = hit.pos;
+            self.pos = hit.pos + hit.normal * 0.05;
+            return BulletImpact::Ricocheted;
+        }
+
+        match material.penetration_cost() {
+            Some(cost) if self.penetration >= cost => {
+                self.penetration -= cost;
+                self.vel *= 1.0 - material.speed_loss();
+                self.damage *= 1.0 - material.speed_loss();
+                self.penetrated.push((hit.grid_x, hit.grid_y));
+                BulletImpact::Penetrated
+            }
+            _ => {
+                self.collisions.push(hit.pos);
+                BulletImpact::Stopped
+            }
+        }
+    }
+
+    // Low cover this close in front of a player protects them from the bullet
+    pub const LOW_COVER_RANGE: f32 = 8.0 * 2.0;
+
+    // Finds low cover the bullet passed over right before reaching the position
+    // Cover crossed on an earlier frame stops the bullet where it was at the start of this one
+    fn low_cover_before(&self, pos: Vec2, tile_map: &TileMap) -> Option<Vec2> {
+        let start = match self.origin.distance(pos) > Bullet::LOW_COVER_RANGE {
+            true => pos - (pos - self.origin).normalize() * Bullet::LOW_COVER_RANGE,
+            false => self.origin,
+        };
+        let cover_pos = tile_map.line_hits_low_cover(start, pos)?;
+        match cover_pos.distance(self.origin) < self.last_pos.distance(self.origin) {
+            true => Some(self.last_pos),
+            false => Some(cover_pos),
+        }
+    }
+
+    // Suppresses the player if the bullet flew close past them this frame
+    // Players are never suppressed by bullets fired from right next to them
+    fn try_suppress(&mut self, player: &mut Player) -> bool {
+        let passed_close =
+            distance_to_segment(player.pos, self.last_pos, self.pos) < Bullet::SUPPRESSION_RADIUS;
+        let fired_from_afar = self.origin.distance(player.pos) > Bullet::SUPPRESSION_RADIUS;
+        if passed_close && fired_from_afar {
+            player
+                .status_effects
+                .add(StatusEffectKind::Suppression, Bullet::SUPPRESSION_TIME);
+            self.has_suppressed = true;
+        }
+        self.has_suppressed
+    }
 }
 
 #[derive(Clone)]
@@ -29,11 +147,122 @@ impl Grenade {
     pub const MAX_ROTATION_SPEED: f32 = 0.15;
 }
 
+// Piece of a tile that flies off when the tile is hit or destroyed
+#[derive(Clone)]
+pub struct Debris {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub rotation: f32,
+    pub size: f32,
+    pub color: Color,
+    pub life: f32, // Seconds left before it disappears
+}
+
+impl Debris {
+    pub const HIT_AMOUNT: u32 = 3;
+    pub const DESTROYED_AMOUNT: u32 = 18;
+    const LIFE_TIME: f32 = 1.5;
+    const FADE_TIME: f32 = 0.3;
+    const DRAG: f32 = 0.9;
+
+    // Sparks thrown off the surface a bullet glanced off of
+    pub fn sparks(pos: Vec2, normal: Vec2) -> Vec<Debris> {
+        (0..Debris::HIT_AMOUNT + 2)
+            .map(|_| Debris {
+                pos,
+                vel: normal.rotate(Vec2::from_angle(rand::gen_range(-1.2, 1.2)))
+                    * rand::gen_range(0.4, 1.2),
+                rotation: rand::gen_range(0.0, std::f32::consts::TAU),
+                size: rand::gen_range(0.2, 0.5),
+                color: Color::from_rgba(255, 210, 90, 255),
+                life: Debris::LIFE_TIME * rand::gen_range(0.1, 0.25),
+            })
+            .collect()
+    }
+
+    // Scatters pieces of the material in every direction
+    pub fn burst(pos: Vec2, material: TileMaterial, amount: u32) -> Vec<Debris> {
+        (0..amount)
+            .map(|_| Debris {
+                pos,
+                vel: Vec2::from_angle(rand::gen_range(0.0, std::f32::consts::TAU))
+                    * rand::gen_range(0.2, 1.0),
+                rotation: rand::gen_range(0.0, std::f32::consts::TAU),
+                size: rand::gen_range(0.4, 1.2),
+                color: material.debris_color(),
+                life: Debris::LIFE_TIME * rand::gen_range(0.5, 1.0),
+            })
+            .collect()
+    }
+
+    fn update(&mut self) {
+        self.pos += self.vel * get_frame_time() * 60.0;
+        self.vel *= Debris::DRAG.powf(get_frame_time() * 60.0);
+        self.life -= get_frame_time();
+    }
+
+    fn draw(&self) {
+        let mut color = self.color;
+        color.a *= (self.life / Debris::FADE_TIME).min(1.0);
+        draw_rectangle_ex(
+            self.pos.x,
+            self.pos.y,
+            self.size,
+            self.size,
+            DrawRectangleParams {
+                offset: Vec2::new(0.5, 0.5),
+                rotation: self.rotation,
+                color,
+            },
+        );
+    }
+}
+
+// Item lying in the world that can be picked up
+#[derive(Clone)]
+pub struct GroundItem {
+    pub pos: Vec2,
+    pub stack: ItemStack,
+}
+
+impl GroundItem {
+    pub const PICKUP_RANGE: f32 = 12.0;
+    const SIZE: f32 = 10.0;
+
+    pub fn draw(&self, assets: &Assets) {
+        draw_circle(
+            self.pos.x + 0.25,
+            self.pos.y + 0.25,
+            GroundItem::SIZE / 3.0,
+            Color::from_rgba(0, 0, 0, 50),
+        );
+        draw_texture_ex(
+            &assets.get_texture(self.stack.item.texture()),
+            self.pos.x - GroundItem::SIZE / 2.0,
+            self.pos.y - GroundItem::SIZE / 2.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(GroundItem::SIZE, GroundItem::SIZE)),
+                ..Default::default()
+            },
+        );
+    }
+}
+
 pub struct EntityManager {
     pub other_players: Vec<Option<Player>>,
     pub other_player_index: u32,
     pub bullets: Vec<Bullet>,
     pub grenades: Vec<Grenade>,
+    pub explosions: Vec<Explosion>,
+    pub debris: Vec<Debris>,
+    pub ground_items: Vec<GroundItem>,
+    pub containers: Vec<Container>,
+    pub doors: Vec<Door>,
+    pub lamps: Vec<Lamp>,
+    pub flashes: Vec<Flash>,
+    pub noises: Vec<Noise>, // Noises made this frame, other than the ones players make themselves
+    pub looting: Option<usize>, // Index of the container the player is searching
 }
 
 impl EntityManager {
@@ -43,54 +272,342 @@ impl EntityManager {
             other_player_index: 0,
             bullets: vec![],
             grenades: vec![],
+            explosions: vec![],
+            debris: vec![],
+            ground_items: vec![],
+            containers: vec![],
+            doors: vec![],
+            lamps: vec![],
+            flashes: vec![],
+            noises: vec![],
+            looting: None,
         }
     }
 
     pub fn explode_grenade(&mut self, grenade: &Grenade) {
-        for _ in 0..60 {
-            self.bullets.push(Bullet {
-                pos: grenade.pos,
-                last_pos: grenade.pos,
-                vel: 3.3 + rand::gen_range(-1.5, 1.5),
-                angle: rand::gen_range(0.0, 2.0 * std::f32::consts::PI),
-                collisions: vec![],
+        self.explosions.push(Explosion::grenade(grenade.pos));
+    }
+
+    // Applies damage, knockback, shake and sound for explosions that happened this frame
+    pub fn handle_explosions(
+        &mut self,
+        assets: &Assets,
+        player: &mut Player,
+        camera: &mut GameCamera,
+        tile_map: &mut TileMap,
+    ) {
+        for explosion in std::mem::take(&mut self.explosions) {
+            // Doors are blown open first so the blast carries on through them
+            for door in &mut self.doors {
+                if door.state == DoorState::Closed && explosion.breaches(door, tile_map) {
+                    door.breach(tile_map);
+                }
+            }
+            for (grid_x, grid_y, material) in explosion.damage_tiles(tile_map) {
+                let tile_pos = Vec2::new(grid_x as f32 * 8.0 + 4.0, grid_y as f32 * 8.0 + 4.0);
+                self.debris
+                    .extend(Debris::burst(tile_pos, material, Debris::DESTROYED_AMOUNT));
+            }
+            explosion.apply_to_player(player, tile_map);
+            for other_player in self.other_players.iter_mut().flatten() {
+                explosion.apply_to_player(other_player, tile_map);
+            }
+            self.bullets.extend(explosion.spawn_fragments());
+            self.flashes.push(Flash::explosion(&explosion));
+            self.noises.push(Noise::explosion(&explosion));
+
+            camera.add_shake(explosion.shake_at(player.pos));
+            assets.sounds.play_at(
+                SoundId::GrenadeExplosion,
+                explosion.pos,
+                Explosion::HEARING_DISTANCE,
+                &Listener::new(player),
+                Some(tile_map),
+            );
+        }
+    }
+
+    pub fn spawn_item(&mut self, pos: Vec2, stack: ItemStack) {
+        self.ground_items.push(GroundItem { pos, stack });
+    }
+
+    // Drops an item near the player's feet
+    pub fn drop_item(&mut self, player: &Player, stack: ItemStack) {
+        let offset = Vec2::new(rand::gen_range(-3.0, 3.0), rand::gen_range(-3.0, 3.0));
+        self.spawn_item(player.pos + offset, stack);
+    }
+
+    // Index of the closest item the player is able to pick up
+    pub fn nearest_pickup(
+        &self,
+        player: &Player,
+        visible_tiles: &HashSet<(u16, u16)>,
+    ) -> Option<usize> {
+        self.ground_items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                item.pos.distance(player.pos) < GroundItem::PICKUP_RANGE
+                    && EntityManager::is_visible(item.pos, visible_tiles)
+            })
+            .min_by(|(_, a), (_, b)| {
+                a.pos
+                    .distance(player.pos)
+                    .total_cmp(&b.pos.distance(player.pos))
+            })
+            .map(|(index, _)| index)
+    }
+
+    // Index of the closest container the player is able to search
+    pub fn nearest_container(
+        &self,
+        player: &Player,
+        visible_tiles: &HashSet<(u16, u16)>,
+    ) -> Option<usize> {
+        self.containers
+            .iter()
+            .enumerate()
+            .filter(|(_, container)| {
+                container.pos.distance(player.pos) < Container::INTERACT_RANGE
+                    && EntityManager::is_visible(container.pos, visible_tiles)
             })
+            .min_by(|(_, a), (_, b)| {
+                a.pos
+                    .distance(player.pos)
+                    .total_cmp(&b.pos.distance(player.pos))
+            })
+            .map(|(index, _)| index)
+    }
+
+    // Index of the closest door the player is able to open or close
+    pub fn nearest_door(&self, player: &Player) -> Option<usize> {
+        self.doors
+            .iter()
+            .enumerate()
+            .filter(|(_, door)| {
+                door.state != DoorState::Breached
+                    && door.pos().distance(player.pos) < Door::INTERACT_RANGE
+            })
+            .min_by(|(_, a), (_, b)| {
+                a.pos()
+                    .distance(player.pos)
+                    .total_cmp(&b.pos().distance(player.pos))
+            })
+            .map(|(index, _)| index)
+    }
+
+    // Handles the interact key: picks up the closest item, starts/stops searching a container,
+    // or opens/closes a door
+    // Whatever doesn't fit in the inventory is left on the ground
+    pub fn handle_interactions(
+        &mut self,
+        assets: &Assets,
+        player: &mut Player,
+        tile_map: &mut TileMap,
+        visible_tiles: &HashSet<(u16, u16)>,
+        console_open: bool,
+    ) {
+        // Searching continues while the player stays next to the container
+        if let Some(index) = self.looting {
+            let container = &mut self.containers[index];
+            if container.pos.distance(player.pos) > Container::INTERACT_RANGE {
+                self.looting = None;
+            } else {
+                container.search(get_frame_time());
+            }
+        }
+
+        if console_open || !is_key_pressed(INTERACT_KEY) || player.inventory.is_open {
+            return;
+        }
+        if self.looting.is_some() {
+            self.looting = None;
+        } else if let Some(index) = self.nearest_pickup(player, visible_tiles) {
+            let stack = self.ground_items[index].stack.clone();
+            match player.inventory.add_item(stack) {
+                Some(leftover) => self.ground_items[index].stack = leftover,
+                None => {
+                    self.ground_items.remove(index);
+                }
+            }
+        } else if let Some(index) = self.nearest_container(player, visible_tiles) {
+            self.looting = Some(index);
+        } else if let Some(index) = self.nearest_door(player) {
+            let doorway = self.doors[index].rect();
+            let doorway_clear = !std::iter::once(&*player)
+                .chain(self.other_players.iter().flatten())
+                .any(|player| player.get_hitbox().overlaps(&doorway));
+            if let Some(sound) = self.doors[index].interact(player, doorway_clear, tile_map) {
+                assets.sounds.play(sound);
+            }
+        }
+    }
+
+    // Moves a revealed item from the container being searched into the player's inventory
+    pub fn loot_item(&mut self, player: &mut Player, item_index: usize) {
+        let Some(container) = self.looting.map(|index| &mut self.containers[index]) else {
+            return;
+        };
+        if let Some(stack) = container.take(item_index) {
+            container.items[item_index] = player.inventory.add_item(stack);
+        }
+    }
+
+    // Separates every pair of overlapping players, including the local player
+    // Each player of a pair moves away by half of the overlap, so walking into someone pushes them along
+    // Separating one pair can push a player into another, so pairs are checked again a few times
+    pub fn handle_player_collisions(&mut self, player: &mut Player, tile_map: &TileMap) {
+        const MAX_PASSES: usize = 4;
+
+        let mut players: Vec<&mut Player> = std::iter::once(player)
+            .chain(self.other_players.iter_mut().flatten())
+            .collect();
+
+        for _ in 0..MAX_PASSES {
+            let mut separated = false;
+            for i in 0..players.len() {
+                let (left, right) = players.split_at_mut(i + 1);
+                let a = &mut left[i];
+                for b in right.iter_mut() {
+                    let offset = b.pos - a.pos;
+                    let overlap = a.collision_radius() + b.collision_radius() - offset.length();
+                    if overlap <= 0.0 {
+                        continue;
+                    }
+                    separated = true;
+                    // Players standing on the exact same spot are pushed apart sideways
+                    let normal = offset.try_normalize().unwrap_or(Vec2::X);
+
+                    // Whatever one player can't move because of a wall, the other one moves instead
+                    let moved_a = a.push(-normal * overlap / 2.0, tile_map);
+                    let left_over = overlap - moved_a.dot(-normal);
+                    let moved_b = b.push(normal * left_over, tile_map);
+                    if moved_b.dot(normal) < left_over {
+                        let left_over = left_over - moved_b.dot(normal);
+                        a.push(-normal * left_over, tile_map);
+                    }
+                }
+            }
+            if !separated {
+                break;
+            }
         }
     }
 
-    pub fn update(&mut self, player: &Player, camera: &GameCamera) {
+    pub fn update(&mut self, player: &Player, camera: &GameCamera, tile_map: &TileMap) {
+        // Other players watch the player while they can see them, and turn towards noises otherwise
         for other_player in self.other_players.iter_mut().flatten() {
-            other_player.turn_to_face(player.pos, camera);
+            if other_player.can_see(player.pos, tile_map) {
+                other_player.turn_to_face(player.pos, camera);
+            } else if let Some(noise) = other_player.loudest_heard() {
+                let pos = noise.pos;
+                other_player.turn_to_face(pos, camera);
+            }
         }
+        for debris in &mut self.debris {
+            debris.update();
+        }
+        self.debris.retain(|debris| debris.life > 0.0);
+        for flash in &mut self.flashes {
+            flash.update();
+        }
+        self.flashes.retain(|flash| flash.time_left > 0.0);
     }
 
-    fn line_collides_with_entity(&self, line: &LineSegment) -> Vec<Vec2> {
-        for player in self.other_players.iter().flatten() {
+    // Lets every player hear the noises made this frame, apart from their own
+    pub fn handle_noises(&mut self, player: &mut Player, tile_map: &TileMap) {
+        let mut noises = std::mem::take(&mut self.noises);
+        for kind in std::mem::take(&mut player.noises_made) {
+            noises.push(Noise::made_by(kind, player, NoiseSource::LocalPlayer));
+        }
+        for (index, other_player) in self.other_players.iter_mut().enumerate() {
+            let Some(other_player) = other_player else {
+                continue;
+            };
+            for kind in std::mem::take(&mut other_player.noises_made) {
+                let source = NoiseSource::OtherPlayer(index);
+                noises.push(Noise::made_by(kind, other_player, source));
+            }
+        }
+
+        player.hear(&noises, NoiseSource::LocalPlayer, tile_map);
+        for (index, other_player) in self.other_players.iter_mut().enumerate() {
+            if let Some(other_player) = other_player {
+                other_player.hear(&noises, NoiseSource::OtherPlayer(index), tile_map);
+            }
+        }
+    }
+
+    // Every light in the world this frame
+    pub fn lights(&self, player: &Player) -> Vec<Light> {
+        let players = std::iter::once(player).chain(self.other_players.iter().flatten());
+        self.lamps
+            .iter()
+            .map(Lamp::light)
+            .chain(self.flashes.iter().map(Flash::light))
+            .chain(players.filter_map(Player::flashlight))
+            .collect()
+    }
+
+    // Returns the index of the player the line hits and where it hit them
+    fn line_collides_with_entity(&self, line: &LineSegment) -> Option<(usize, Vec<Vec2>)> {
+        for (index, player) in self.other_players.iter().enumerate() {
+            let Some(player) = player else { continue };
             let intersects = line.line_intersects_rect(player.get_hitbox());
             if !intersects.is_empty() {
-                return intersects;
+                return Some((index, intersects));
             }
         }
-        vec![]
+        None
     }
 
-    pub fn draw_entities(&mut self, assets: &Assets, player: &Player, tile_map: &TileMap) {
-        let is_visible = |pos: Vec2, visible_tiles: &HashSet<(u16, u16)>| -> bool {
-            let dist_to_player = {
-                let dx = pos.x - player.pos.x;
-                let dy = pos.y - player.pos.y;
-                (dx * dx + dy * dy).sqrt()
-            };
-            visible_tiles.contains(&((pos.x / 8.0) as u16, (pos.y / 8.0) as u16))
-                || dist_to_player < 18.0
-        };
+    // Whether the position is on one of the tiles the player can see
+    fn is_visible(pos: Vec2, visible_tiles: &HashSet<(u16, u16)>) -> bool {
+        visible_tiles.contains(&((pos.x / 8.0) as u16, (pos.y / 8.0) as u16))
+    }
 
-        // Get tiles visible to camera
-        let visible_tiles = tile_map.find_tiles(
-            player.get_player_rays(std::f32::consts::PI, crate::world::LINE_LENGTH),
-            crate::world::LINE_LENGTH / 8.0 * 1.0,
-            player.pos,
-        );
+    // Drawn under the shadows, so like walls they stay on screen out of sight, only darker
+    pub fn draw_fixtures(&self) {
+        // Doors are always drawn, like the walls they're in
+        for door in &self.doors {
+            door.draw();
+        }
+        for lamp in &self.lamps {
+            lamp.draw();
+        }
+    }
+
+    // Entities hidden out of sight, the shadow mask clips them so they don't need culling here
+    pub fn draw_entities(&self, assets: &Assets) {
+        // Draw containers
+        for container in &self.containers {
+            container.draw();
+        }
+
+        // Draw items on the ground
+        for ground_item in &self.ground_items {
+            ground_item.draw(assets);
+        }
+
+        // Draw debris
+        for debris in &self.debris {
+            debris.draw();
+        }
+
+        // Draw grenades
+        for grenade in &self.grenades {
+            draw_texture_ex(
+                &assets.get_texture(TextureId::GrenadeUnpinned),
+                grenade.pos.x - 7.0,
+                grenade.pos.y - 7.0,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(Vec2::new(14.0, 14.0)),
+                    rotation: grenade.rotation,
+                    ..Default::default()
+                },
+            );
+        }
 
         // Draw bullets
         for bullet in &self.bullets {
@@ -125,28 +642,9 @@ impl EntityManager {
             }
         }
 
-        // Draw grenades
-        for grenade in &self.grenades {
-            if is_visible(grenade.pos, &visible_tiles) {
-                draw_texture_ex(
-                    &assets.get_texture("grenade_unpinned.png"),
-                    grenade.pos.x - 7.0,
-                    grenade.pos.y - 7.0,
-                    WHITE,
-                    DrawTextureParams {
-                        dest_size: Some(Vec2::new(14.0, 14.0)),
-                        rotation: grenade.rotation,
-                        ..Default::default()
-                    },
-                )
-            }
-        }
-
         // Draw players
         for other_player in self.other_players.iter().flatten() {
-            if is_visible(other_player.pos, &visible_tiles) {
-                other_player.draw(assets);
-            }
+            other_player.draw(assets);
         }
     }
 
@@ -161,69 +659,75 @@ impl EntityManager {
             .insert(self.other_player_index as usize, Some(player));
     }
 
+    // Fires one shot of the player's gun from the barrel, spreading the bullets around the aim angle
+    pub fn fire_gun(
+        &mut self,
+        sounds: &SoundLibrary,
+        player: &Player,
+        gun: &Item::Gun,
+        bullet_pos: Vec2,
+        aim_angle: f32,
+    ) {
+        let bullet_spread = gun.bullet_spread * player.spread_multiplier();
+        for _ in 0..gun.bullets_per_shot {
+            let bullet_speed =
+                gun.bullet_speed + rand::gen_range(-gun.bullet_spread, gun.bullet_spread); // Apply speed spread
+            let angle = aim_angle + rand::gen_range(-bullet_spread, bullet_spread);
+
+            self.bullets.push(Bullet::new(
+                bullet_pos,
+                bullet_speed,
+                angle,
+                gun.bullet_damage,
+                gun.penetration,
+            ));
+        }
+        if let Some(bullet) = self.bullets.last() {
+            self.flashes.push(Flash::muzzle(bullet.origin));
+        }
+        self.noises.push(Noise::made_by(
+            NoiseKind::Gunshot,
+            player,
+            NoiseSource::LocalPlayer,
+        ));
+        sounds.play(SoundId::GunShot(gun.name));
+    }
+
     pub async fn handle_shooting(
         &mut self,
         assets: &Assets,
-        player: &Player,
+        player: &mut Player,
         camera: &GameCamera,
-        tile_map: &TileMap,
+        tile_map: &mut TileMap,
+        console_open: bool,
     ) {
-        let is_shooting = (is_mouse_button_pressed(MouseButton::Left)
-            | is_key_pressed(KeyCode::Space))
-            && is_mouse_button_down(MouseButton::Right);
+        let is_shooting = player.is_shooting() && !console_open;
 
         // Remove old bullets that hit somthing
         self.bullets.retain(|bullet| bullet.collisions.is_empty());
 
         // Handle spawning bullets
-        if is_shooting {
-            for _ in 0..player.inventory.gun.bullets_per_shot {
-                let bullet_speed = player.inventory.gun.bullet_speed
-                    + rand::gen_range(
-                        -player.inventory.gun.bullet_spread,
-                        player.inventory.gun.bullet_spread,
-                    ); // Apply speed spread
-
-                let mouse_pos: Vec2 = mouse_position().into();
-                let mouse_dist_center = mouse_pos - camera.world_to_screen(player.pos);
-                let angle = f32::atan2(mouse_dist_center.x, mouse_dist_center.y);
-
-                let (barrel_offset_x, barrel_offset_y) = (
-                    player.inventory.gun.barrel_offset.x,
-                    player.inventory.gun.barrel_offset.y,
-                );
-
-                let bullet_pos = Vec2 {
-                    x: player.pos.x + barrel_offset_x * -angle.cos()
-                        - barrel_offset_y * angle.sin(),
-                    y: player.pos.y
-                        + barrel_offset_x * angle.sin()
-                        + barrel_offset_y * -angle.cos(),
-                };
-                let mut new_angle = angle;
-                let dist_from_player = (mouse_dist_center.x * mouse_dist_center.x
-                    + mouse_dist_center.y * mouse_dist_center.y)
-                    .sqrt();
-                if dist_from_player > 80.0 {
-                    let mouse_dist_center = mouse_pos - camera.world_to_screen(bullet_pos);
-                    new_angle = f32::atan2(mouse_dist_center.x, mouse_dist_center.y);
-                }
+        if let Some(gun) = player.inventory.gun().filter(|_| is_shooting) {
+            let mouse_pos: Vec2 = mouse_position().into();
+            let mouse_dist_center = mouse_pos - camera.world_to_screen(player.pos);
+            let angle = f32::atan2(mouse_dist_center.x, mouse_dist_center.y);
 
-                new_angle += rand::gen_range(
-                    -player.inventory.gun.bullet_spread,
-                    player.inventory.gun.bullet_spread,
-                );
+            let (barrel_offset_x, barrel_offset_y) = (gun.barrel_offset.x, gun.barrel_offset.y);
 
-                self.bullets.push(Bullet {
-                    pos: bullet_pos,
-                    vel: bullet_speed,
-                    collisions: vec![],
-                    angle: new_angle,
-                    last_pos: bullet_pos,
-                });
+            let bullet_pos = Vec2 {
+                x: player.pos.x + barrel_offset_x * -angle.cos() - barrel_offset_y * angle.sin(),
+                y: player.pos.y + barrel_offset_x * angle.sin() + barrel_offset_y * -angle.cos(),
+            };
+            let mut new_angle = angle;
+            let dist_from_player = (mouse_dist_center.x * mouse_dist_center.x
+                + mouse_dist_center.y * mouse_dist_center.y)
+                .sqrt();
+            if dist_from_player > 80.0 {
+                let mouse_dist_center = mouse_pos - camera.world_to_screen(bullet_pos);
+                new_angle = f32::atan2(mouse_dist_center.x, mouse_dist_center.y);
             }
-            let sound_name = format!("{}{}", player.inventory.gun.name, "_shooting.wav");
-            assets.play_sound(&sound_name);
+
+            self.fire_gun(&assets.sounds, player, gun, bullet_pos, new_angle);
         }
 
         // Grenades
@@ -252,7 +756,24 @@ impl EntityManager {
                 * 60.0;
         }
 
+        // Suppress players that bullets fly close past
+        for bullet in self
+            .bullets
+            .iter_mut()
+            .filter(|bullet| !bullet.has_suppressed)
+        {
+            if !bullet.try_suppress(player) {
+                for other_player in self.other_players.iter_mut().flatten() {
+                    if bullet.try_suppress(other_player) {
+                        break;
+                    }
+                }
+            }
+        }
+
         let mut new_bullets = self.bullets.clone();
+        let mut hits = vec![];
+        let mut impacts = vec![];
         new_bullets.iter_mut().for_each(|bullet| {
             let line = &LineSegment {
                 x1: bullet.last_pos.x,
@@ -263,10 +784,86 @@ impl EntityManager {
             if bullet.vel.abs() <= 0.00 {
                 bullet.collisions.push(bullet.pos);
             }
-            bullet.collisions.extend(tile_map.line_collides_with_tile(line));
-            bullet.collisions.extend(self.line_collides_with_entity(line));
+            // Follow the bullet through every tile it reaches before any player it hits
+            // Players behind a tile that stopped or deflected the bullet are safe
+            let start = bullet.last_pos;
+            let entity_hit = self.line_collides_with_entity(line);
+            let entity_distance = entity_hit
+                .iter()
+                .flat_map(|(_, intersects)| intersects)
+                .map(|pos| pos.distance(start))
+                .fold(f32::INFINITY, f32::min);
+            let mut blocked = false;
+            while let Some(hit) = tile_map
+                .line_hits_tile(bullet.last_pos, bullet.pos, &bullet.penetrated)
+                .filter(|hit| hit.pos.distance(start) < entity_distance)
+            {
+                let material = tile_map.material(hit.grid_x, hit.grid_y);
+                let damage = bullet.damage;
+                let impact = bullet.impact(&hit, material);
+                impacts.push((hit, material, damage, impact));
+                if impact != BulletImpact::Penetrated {
+                    blocked = true;
+                    break;
+                }
+            }
+            if let Some((index, intersects)) = entity_hit.filter(|_| !blocked) {
+                // Crouching and prone players are safe behind low cover, standing ones are shot over it
+                let cover_pos = match &self.other_players[index] {
+                    Some(hit_player) if hit_player.stance != PlayerStance::Standing => {
+                        bullet.low_cover_before(hit_player.pos, tile_map)
+                    }
+                    _ => None,
+                };
+                match cover_pos {
+                    Some(cover_pos) => bullet.collisions.push(cover_pos),
+                    None => {
+                        bullet.collisions.extend(intersects);
+                        hits.push((index, bullet.damage));
+                    }
+                }
+            }
         });
         self.bullets = new_bullets;
+
+        // Damage tiles hit by bullets, every hit chips a few pieces off or throws sparks
+        for (hit, material, damage, impact) in impacts {
+            // Just outside the face that was hit, so the wall itself never muffles its own impact sound
+            let effect_pos = hit.pos + hit.normal * 0.5;
+            let sound = match impact {
+                BulletImpact::Ricocheted => {
+                    self.debris.extend(Debris::sparks(effect_pos, hit.normal));
+                    SoundId::Ricochet
+                }
+                BulletImpact::Stopped | BulletImpact::Penetrated => {
+                    self.debris
+                        .extend(Debris::burst(effect_pos, material, Debris::HIT_AMOUNT));
+                    material.impact_sound()
+                }
+            };
+            assets.sounds.play_at(
+                sound,
+                effect_pos,
+                Bullet::IMPACT_HEARING_DISTANCE,
+                &Listener::new(player),
+                Some(tile_map),
+            );
+
+            if tile_map.damage_tile(hit.grid_x, hit.grid_y, damage) {
+                let tile_pos =
+                    Vec2::new(hit.grid_x as f32 * 8.0 + 4.0, hit.grid_y as f32 * 8.0 + 4.0);
+                self.debris
+                    .extend(Debris::burst(tile_pos, material, Debris::DESTROYED_AMOUNT));
+            }
+        }
+
+        // Damage players hit by bullets
+        for (index, damage) in hits {
+            if let Some(Some(hit_player)) = self.other_players.get_mut(index) {
+                hit_player.take_damage(damage);
+            }
+        }
+
         let grenades = self.grenades.clone();
         for grenade in grenades {
             if grenade.fuse_time <= 0.0 {
@@ -277,3 +874,27 @@ impl EntityManager {
         self.grenades.retain(|grenade| grenade.fuse_time > 0.0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioBackend, Mixer};
+
+    #[test]
+    fn firing_the_sniper_plays_its_shot() {
+        let mut sounds = SoundLibrary::new(Mixer::new(AudioBackend::new(true)));
+        let bytes = std::fs::read("assets/sounds/sniper_shooting.wav").unwrap();
+        sounds
+            .insert("sniper_shooting.wav".to_string(), bytes)
+            .unwrap();
+        let mut entities = EntityManager::new();
+        let player = Player::new(10, 10);
+        let sniper = Item::Gun::sniper();
+
+        entities.fire_gun(&sounds, &player, &sniper, player.pos, 0.0);
+
+        assert_eq!(sounds.mixer.backend.played(), ["sniper_shooting.wav"]);
+        assert_eq!(entities.bullets.len(), sniper.bullets_per_shot as usize);
+        assert_eq!(entities.noises[0].kind, NoiseKind::Gunshot);
+    }
+}
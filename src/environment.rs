@@ -0,0 +1,224 @@
+use crate::{assets::Assets, camera::GameCamera, manifest::SoundId};
+use macroquad::prelude::*;
+use rodio::Sink;
+use std::f32::consts::TAU;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Fog,
+}
+
+impl Weather {
+    pub fn from_name(name: &str) -> Option<Weather> {
+        match name {
+            "clear" => Some(Weather::Clear),
+            "rain" => Some(Weather::Rain),
+            "fog" => Some(Weather::Fog),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weather::Clear => "clear",
+            Weather::Rain => "rain",
+            Weather::Fog => "fog",
+        }
+    }
+
+    // Multiplier for how far players can see
+    pub fn vision_multiplier(&self) -> f32 {
+        match self {
+            Weather::Clear => 1.0,
+            Weather::Rain => 0.8,
+            Weather::Fog => 0.55,
+        }
+    }
+
+    // Multiplier for the ambient light, clouds block some of the sun
+    fn light_multiplier(&self) -> f32 {
+        match self {
+            Weather::Clear => 1.0,
+            Weather::Rain => 0.75,
+            Weather::Fog => 0.9,
+        }
+    }
+}
+
+// Rain drop falling towards the ground, it splashes once it lands
+struct Raindrop {
+    pos: Vec2,
+    fall_time: f32, // Time left until it lands
+    splash_time: f32,
+}
+
+impl Raindrop {
+    const FALL_TIME: f32 = 0.35;
+    const SPLASH_TIME: f32 = 0.12;
+    const VELOCITY: Vec2 = Vec2::new(25.0, 110.0); // Per second, rain falls at a slant
+}
+
+// Time of day and weather of a map
+pub struct Environment {
+    pub time: f32,       // Hours since midnight
+    pub day_length: f32, // Seconds a full day takes
+    pub weather: Weather,
+    raindrops: Vec<Raindrop>,
+    rain_sound: Option<Sink>,
+}
+
+impl Environment {
+    const RAIN_PER_SECOND: f32 = 900.0;
+    const RAIN_VOLUME: f32 = 0.35;
+    const FOG_COLOR: Color = Color::new(0.72, 0.74, 0.78, 0.35);
+    const NIGHT_LIGHT: Color = Color::new(0.16, 0.19, 0.33, 1.0);
+    const DUSK_LIGHT: Color = Color::new(0.95, 0.62, 0.45, 1.0);
+
+    pub fn new(time: f32, day_length: f32, weather: Weather) -> Self {
+        Self {
+            time,
+            day_length,
+            weather,
+            raindrops: vec![],
+            rain_sound: None,
+        }
+    }
+
+    // Height of the sun, 1.0 at midday and -1.0 at midnight
+    fn sun_height(&self) -> f32 {
+        -(self.time / 24.0 * TAU).cos()
+    }
+
+    // Ambient light at the current time, going from the map's daylight to night through dusk
+    pub fn ambient_light(&self, daylight: Color) -> Color {
+        let smoothstep = |edge0: f32, edge1: f32, x: f32| {
+            let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        };
+        let sun = self.sun_height();
+        let day = Environment::DUSK_LIGHT
+            .to_vec()
+            .lerp(daylight.to_vec(), smoothstep(0.05, 0.5, sun));
+        let light = Environment::NIGHT_LIGHT
+            .to_vec()
+            .lerp(day, smoothstep(-0.15, 0.2, sun));
+        let light = light * self.weather.light_multiplier();
+        Color::new(light.x, light.y, light.z, 1.0)
+    }
+
+    pub fn update(&mut self, assets: &Assets, camera: &GameCamera) {
+        self.time = (self.time + get_frame_time() / self.day_length * 24.0).rem_euclid(24.0);
+
+        // Rain sound loops for as long as it's raining
+        match self.weather {
+            Weather::Rain if self.rain_sound.is_none() => {
+                self.rain_sound = assets
+                    .sounds
+                    .play_looping(SoundId::Rain, Environment::RAIN_VOLUME);
+            }
+            Weather::Rain => {}
+            _ => self.rain_sound = None,
+        }
+
+        for drop in &mut self.raindrops {
+            if drop.fall_time > 0.0 {
+                drop.fall_time -= get_frame_time();
+                drop.pos += Raindrop::VELOCITY * get_frame_time();
+            } else {
+                drop.splash_time -= get_frame_time();
+            }
+        }
+        self.raindrops.retain(|drop| drop.splash_time > 0.0);
+        if self.weather == Weather::Rain {
+            self.spawn_rain(camera);
+        }
+    }
+
+    // Drops are spawned all over the screen, a bit above it so the top edge doesn't look empty
+    fn spawn_rain(&mut self, camera: &GameCamera) {
+        let top_left = camera.screen_to_world(Vec2::ZERO);
+        let bottom_right = camera.screen_to_world(Vec2::new(screen_width(), screen_height()));
+        let (min, max) = (top_left.min(bottom_right), top_left.max(bottom_right));
+        let min = min - Raindrop::VELOCITY * Raindrop::FALL_TIME;
+
+        // Rain is spread over the area of the screen, so zooming out shows more drops
+        let area = (max - min).x * (max - min).y;
+        let amount = Environment::RAIN_PER_SECOND * get_frame_time() * area / (8.0 * 8.0 * 400.0);
+        let amount =
+            amount.floor() as usize + (rand::gen_range(0.0, 1.0) < amount.fract()) as usize;
+        for _ in 0..amount {
+            self.raindrops.push(Raindrop {
+                pos: Vec2::new(rand::gen_range(min.x, max.x), rand::gen_range(min.y, max.y)),
+                fall_time: rand::gen_range(0.5, 1.0) * Raindrop::FALL_TIME,
+                splash_time: Raindrop::SPLASH_TIME,
+            });
+        }
+    }
+
+    // Drawn over the world, in world space
+    pub fn draw(&self) {
+        for drop in &self.raindrops {
+            if drop.fall_time > 0.0 {
+                let tail = drop.pos - Raindrop::VELOCITY * 0.025;
+                let color = Color::new(0.7, 0.76, 0.88, 0.45);
+                draw_line(tail.x, tail.y, drop.pos.x, drop.pos.y, 0.15, color);
+            } else {
+                let progress = 1.0 - drop.splash_time / Raindrop::SPLASH_TIME;
+                let color = Color::new(0.7, 0.76, 0.88, 0.5 * (1.0 - progress));
+                draw_circle_lines(drop.pos.x, drop.pos.y, 0.3 + progress * 0.9, 0.1, color);
+            }
+        }
+    }
+
+    // Drawn over the world, in screen space
+    pub fn draw_fog(&self) {
+        if self.weather == Weather::Fog {
+            draw_rectangle(
+                0.0,
+                0.0,
+                screen_width(),
+                screen_height(),
+                Environment::FOG_COLOR,
+            );
+        }
+    }
+
+    // Runs a console command, returning what to print back
+    // "time <hours>", "daylength <seconds>" and "weather <clear|rain|fog>"
+    pub fn run_command(&mut self, words: &[&str]) -> Result<String, String> {
+        let value = || words.get(1).ok_or("missing value");
+        match words[0] {
+            "time" => {
+                let time = parse_finite(value()?).ok_or("expected hours")?;
+                self.time = time.rem_euclid(24.0);
+                Ok(format!("Time set to {}", self.clock()))
+            }
+            "daylength" => {
+                let day_length = parse_finite(value()?).ok_or("expected seconds")?;
+                if day_length <= 0.0 {
+                    return Err("day length has to be positive".to_string());
+                }
+                self.day_length = day_length;
+                Ok(format!("Days now take {day_length} seconds"))
+            }
+            "weather" => {
+                self.weather = Weather::from_name(value()?).ok_or("unknown weather")?;
+                Ok(format!("Weather set to {}", self.weather.name()))
+            }
+            _ => Err(format!("unknown command '{}'", words[0])),
+        }
+    }
+
+    // Time of day as hours and minutes
+    pub fn clock(&self) -> String {
+        let minutes = (self.time * 60.0) as u32;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+// Rust parses "nan" and "inf" as numbers, which would break the clock
+fn parse_finite(value: &str) -> Option<f32> {
+    value.parse().ok().filter(|value: &f32| value.is_finite())
+}
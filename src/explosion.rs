@@ -0,0 +1,137 @@
+use crate::{
+    doors::Door, entities::Bullet, materials::TileMaterial, player::Player,
+    status_effects::StatusEffectKind, tile_map::TileMap,
+};
+use macroquad::prelude::*;
+
+#[derive(Clone)]
+pub struct Explosion {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub max_damage: f32,
+    pub knockback: f32,
+    pub fragments: u32,
+    pub fragment_damage: f32,
+    pub shake: f32,
+}
+
+impl Explosion {
+    // Past this distance the explosion can no longer be heard
+    pub const HEARING_DISTANCE: f32 = 8.0 * 60.0;
+    pub const MAX_CONCUSSION_TIME: f32 = 6.0;
+    // Fragments shatter glass but get stopped by anything thicker
+    pub const FRAGMENT_PENETRATION: f32 = 0.1;
+
+    pub fn grenade(pos: Vec2) -> Explosion {
+        Explosion {
+            pos,
+            radius: 8.0 * 5.0,
+            max_damage: 120.0,
+            knockback: 1.4,
+            fragments: 16,
+            fragment_damage: 6.0,
+            shake: 1.0,
+        }
+    }
+
+    // 1.0 at the center of the explosion, 0.0 at and past its radius
+    pub fn falloff(&self, pos: Vec2) -> f32 {
+        let dist = self.pos.distance(pos);
+        (1.0 - dist / self.radius).clamp(0.0, 1.0)
+    }
+
+    // Walls between the explosion and the position fully absorb the blast
+    pub fn is_occluded(&self, pos: Vec2, tile_map: &TileMap) -> bool {
+        !tile_map.has_line_of_sight(self.pos, pos)
+    }
+
+    pub fn damage_at(&self, pos: Vec2, tile_map: &TileMap) -> f32 {
+        if self.is_occluded(pos, tile_map) {
+            return 0.0;
+        }
+        // Quadratic falloff so only players close to the blast take heavy damage
+        self.max_damage * self.falloff(pos).powi(2)
+    }
+
+    pub fn knockback_at(&self, pos: Vec2, tile_map: &TileMap) -> Vec2 {
+        if self.is_occluded(pos, tile_map) {
+            return Vec2::ZERO;
+        }
+        (pos - self.pos).normalize_or_zero() * self.knockback * self.falloff(pos)
+    }
+
+    // Checks if the blast gets to the tile without going through another wall first
+    fn reaches_tile(&self, grid_x: u16, grid_y: u16, tile_map: &TileMap) -> bool {
+        let tile_pos = Vec2::new(grid_x as f32 * 8.0 + 4.0, grid_y as f32 * 8.0 + 4.0);
+        match tile_map.first_collider_on_line(self.pos, tile_pos) {
+            Some(tile) => tile == (grid_x, grid_y),
+            None => true,
+        }
+    }
+
+    // Doors close to the explosion are blown open, unless a wall is in the way
+    pub fn breaches(&self, door: &Door, tile_map: &TileMap) -> bool {
+        self.pos.distance(door.pos()) <= Door::BREACH_RADIUS
+            && self.reaches_tile(door.grid_x, door.grid_y, tile_map)
+    }
+
+    // Damages the destructible tiles the blast reaches
+    // Returns the tiles that were destroyed, along with what they were made of
+    pub fn damage_tiles(&self, tile_map: &mut TileMap) -> Vec<(u16, u16, TileMaterial)> {
+        let radius = (self.radius / 8.0).ceil() as i32;
+        let (center_x, center_y) = ((self.pos.x / 8.0) as i32, (self.pos.y / 8.0) as i32);
+        let mut destroyed = vec![];
+
+        for grid_y in (center_y - radius).max(0)..=center_y + radius {
+            for grid_x in (center_x - radius).max(0)..=center_x + radius {
+                let (grid_x, grid_y) = (grid_x as u16, grid_y as u16);
+                let material = tile_map.material(grid_x, grid_y);
+                if material.hit_points().is_none() || !self.reaches_tile(grid_x, grid_y, tile_map) {
+                    continue;
+                }
+                let tile_pos = Vec2::new(grid_x as f32 * 8.0 + 4.0, grid_y as f32 * 8.0 + 4.0);
+                let damage = self.max_damage * self.falloff(tile_pos);
+                if damage > 0.0 && tile_map.damage_tile(grid_x, grid_y, damage) {
+                    destroyed.push((grid_x, grid_y, material));
+                }
+            }
+        }
+        destroyed
+    }
+
+    pub fn apply_to_player(&self, player: &mut Player, tile_map: &TileMap) {
+        let damage = self.damage_at(player.pos, tile_map);
+        if damage > 0.0 {
+            player.take_damage(damage);
+            player.vel += self.knockback_at(player.pos, tile_map);
+            player.status_effects.add(
+                StatusEffectKind::Concussion,
+                Explosion::MAX_CONCUSSION_TIME * self.falloff(player.pos),
+            );
+        }
+    }
+
+    // Camera shake felt by a listener at the position
+    pub fn shake_at(&self, pos: Vec2) -> f32 {
+        let dist = self.pos.distance(pos);
+        self.shake * (1.0 - dist / Explosion::HEARING_DISTANCE).clamp(0.0, 1.0)
+    }
+
+    // Fragments are evenly spread around the explosion with a bit of jitter
+    pub fn spawn_fragments(&self) -> Vec<Bullet> {
+        let angle_increment = 2.0 * std::f32::consts::PI / self.fragments as f32;
+        (0..self.fragments)
+            .map(|fragment| {
+                let angle = angle_increment * fragment as f32
+                    + rand::gen_range(-angle_increment, angle_increment) / 2.0;
+                Bullet::new(
+                    self.pos,
+                    3.3 + rand::gen_range(-1.0, 1.0),
+                    angle,
+                    self.fragment_damage,
+                    Explosion::FRAGMENT_PENETRATION,
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{player::Player, tile_map::TileMap};
+use macroquad::prelude::*;
+use std::{collections::HashSet, f32::consts::TAU, time::Instant};
+
+// Symmetric shadowcasting, see https://www.albertford.com/shadowcasting/
+// Slopes are exact fractions so the result never depends on float precision or build settings
+
+// Slope of a line from the origin, as numerator / denominator (the denominator is always positive)
+#[derive(Clone, Copy)]
+struct Slope {
+    num: i32,
+    den: i32,
+}
+
+// Row of tiles at the same distance from the origin, within one quadrant
+struct Row {
+    depth: i32,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    // Columns of the tiles in the row that lie between its start and end slopes
+    fn columns(&self) -> std::ops::RangeInclusive<i32> {
+        // Rounds depth * slope to the nearest column, with ties going up or down
+        let round_up =
+            |slope: Slope| (2 * self.depth * slope.num + slope.den).div_euclid(2 * slope.den);
+        let round_down =
+            |slope: Slope| -(slope.den - 2 * self.depth * slope.num).div_euclid(2 * slope.den);
+        round_up(self.start)..=round_down(self.end)
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    // Slope of the edge of the tile closest to the start of the row
+    fn tile_slope(&self, col: i32) -> Slope {
+        Slope {
+            num: 2 * col - 1,
+            den: 2 * self.depth,
+        }
+    }
+
+    // Floor tiles are only seen when their center lies inside of the row, which keeps vision symmetric
+    fn is_symmetric(&self, col: i32) -> bool {
+        col * self.start.den >= self.depth * self.start.num
+            && col * self.end.den <= self.depth * self.end.num
+    }
+}
+
+// One of the four 90 degree sections the area around the origin is split into
+#[derive(Clone, Copy)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    const ALL: [Quadrant; 4] = [
+        Quadrant::North,
+        Quadrant::East,
+        Quadrant::South,
+        Quadrant::West,
+    ];
+
+    // Grid position of a tile given by its depth and column inside of the quadrant
+    fn transform(&self, origin: (i32, i32), depth: i32, col: i32) -> (i32, i32) {
+        match self {
+            Quadrant::North => (origin.0 + col, origin.1 - depth),
+            Quadrant::South => (origin.0 + col, origin.1 + depth),
+            Quadrant::East => (origin.0 + depth, origin.1 + col),
+            Quadrant::West => (origin.0 - depth, origin.1 + col),
+        }
+    }
+}
+
+impl TileMap {
+    // Tiles visible from the origin within a cone around the facing direction
+    // Walls are included so the edges of rooms are lit
+    pub fn field_of_view(
+        &self,
+        origin: Vec2,
+        facing: Vec2,
+        fov: f32,
+        length: f32,
+    ) -> HashSet<(u16, u16)> {
+        let origin_tile = (
+            (origin.x / 8.0).floor() as i32,
+            (origin.y / 8.0).floor() as i32,
+        );
+        let max_depth = (length / 8.0).ceil() as i32;
+        let mut visible = HashSet::with_capacity(700);
+
+        let mut reveal = |(x, y): (i32, i32)| {
+            if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+                return;
+            }
+            let tile_pos = Vec2::new(x as f32 * 8.0 + 4.0, y as f32 * 8.0 + 4.0);
+            if (x, y) == origin_tile
+                || TileMap::in_vision_cone(origin, tile_pos, facing, fov, length)
+            {
+                visible.insert((x as u16, y as u16));
+            }
+        };
+        reveal(origin_tile);
+
+        for quadrant in Quadrant::ALL {
+            let first_row = Row {
+                depth: 1,
+                start: Slope { num: -1, den: 1 },
+                end: Slope { num: 1, den: 1 },
+            };
+            self.scan_row(quadrant, origin_tile, first_row, max_depth, &mut reveal);
+        }
+        visible
+    }
+
+    // Outline of the area visible from the origin within a cone around the facing direction
+    // Points are ordered by angle, so together with the origin they form a triangle fan
+    // Rays are cast at every wall corner in range, which lines the shadow edges up exactly with the walls
+    pub fn visibility_polygon(
+        &self,
+        origin: Vec2,
+        facing: Vec2,
+        fov: f32,
+        length: f32,
+    ) -> Vec<Vec2> {
+        const CORNER_OFFSET: f32 = 0.0005; // Rays just past a corner find what's behind it
+        const ARC_STEP: f32 = 0.05; // Angle between the rays rounding off the far edge
+
+        let fov = fov.min(TAU);
+        let half_fov = fov / 2.0;
+        let arc_rays = (fov / ARC_STEP).ceil().max(1.0) as usize;
+        let mut angles: Vec<f32> = (0..=arc_rays)
+            .map(|i| -half_fov + fov * i as f32 / arc_rays as f32)
+            .collect();
+
+        let min = ((origin - length) / 8.0).floor().max(Vec2::ZERO);
+        let max = ((origin + length) / 8.0).ceil();
+        for y in min.y as u16..=(max.y as u16).min(self.height) {
+            for x in min.x as u16..=(max.x as u16).min(self.width) {
+                if !self.is_wall_corner(x, y) {
+                    continue;
+                }
+                let offset = Vec2::new(x as f32 * 8.0, y as f32 * 8.0) - origin;
+                if offset.length() > length {
+                    continue;
+                }
+                let angle = facing.angle_between(offset);
+                for angle in [angle - CORNER_OFFSET, angle, angle + CORNER_OFFSET] {
+                    if angle.abs() <= half_fov {
+                        angles.push(angle);
+                    }
+                }
+            }
+        }
+        angles.sort_by(|a, b| a.total_cmp(b));
+
+        let facing_angle = facing.y.atan2(facing.x);
+        angles
+            .into_iter()
+            .map(|angle| {
+                let end = origin + Vec2::from_angle(facing_angle + angle) * length;
+                self.line_hits_tile(origin, end, &[])
+                    .map_or(end, |hit| hit.pos)
+            })
+            .collect()
+    }
+
+    // Checks if the outline of the walls turns at the grid corner, at the top left of the tile
+    // Straight wall edges don't need rays, only the corners where shadows can start
+    fn is_wall_corner(&self, grid_x: u16, grid_y: u16) -> bool {
+        let (left, up) = (grid_x.wrapping_sub(1), grid_y.wrapping_sub(1));
+        let around = [
+            self.is_collider(left, up),
+            self.is_collider(grid_x, up),
+            self.is_collider(left, grid_y),
+            self.is_collider(grid_x, grid_y),
+        ];
+        match around.iter().filter(|&&wall| wall).count() {
+            1 | 3 => true,
+            // Two walls only touching diagonally
+            2 => around[0] == around[3],
+            _ => false,
+        }
+    }
+
+    // Checks if the tile is within range, and its center is close enough to the facing direction
+    // Tiles get a bit of leeway for their size so the edges of the cone don't flicker
+    fn in_vision_cone(origin: Vec2, tile_pos: Vec2, facing: Vec2, fov: f32, length: f32) -> bool {
+        let offset = tile_pos - origin;
+        let dist = offset.length();
+        if dist > length {
+            return false;
+        }
+        let leeway = (4.0 * std::f32::consts::SQRT_2 / dist.max(1.0)).atan();
+        facing.angle_between(offset).abs() <= fov / 2.0 + leeway
+    }
+
+    fn scan_row(
+        &self,
+        quadrant: Quadrant,
+        origin: (i32, i32),
+        mut row: Row,
+        max_depth: i32,
+        reveal: &mut impl FnMut((i32, i32)),
+    ) {
+        if row.depth > max_depth {
+            return;
+        }
+        let is_wall = |col: i32| {
+            let (x, y) = quadrant.transform(origin, row.depth, col);
+            x < 0 || y < 0 || self.is_collider(x as u16, y as u16)
+        };
+
+        let mut prev_is_wall = None;
+        for col in row.columns() {
+            let wall = is_wall(col);
+            if wall || row.is_symmetric(col) {
+                reveal(quadrant.transform(origin, row.depth, col));
+            }
+            match (prev_is_wall, wall) {
+                // Coming out from behind a wall, the row starts here
+                (Some(true), false) => row.start = row.tile_slope(col),
+                // Running into a wall, everything up to here is visible in the next row
+                (Some(false), true) => {
+                    let mut next_row = row.next();
+                    next_row.end = row.tile_slope(col);
+                    self.scan_row(quadrant, origin, next_row, max_depth, reveal);
+                }
+                _ => {}
+            }
+            prev_is_wall = Some(wall);
+        }
+        if prev_is_wall == Some(false) {
+            self.scan_row(quadrant, origin, row.next(), max_depth, reveal);
+        }
+    }
+
+    // Times shadowcasting against the old ray based vision from the player's view
+    // Results are printed to the console
+    pub fn benchmark_fov(&self, player: &Player) {
+        const ITERATIONS: u32 = 200;
+
+        let rays_start = Instant::now();
+        let mut ray_tiles = HashSet::new();
+        for _ in 0..ITERATIONS {
+            ray_tiles = self.find_tiles(
+                player.get_player_rays(player.vision_fov(), player.vision_length()),
+                player.vision_length() / 8.0,
+                player.pos,
+            );
+        }
+        let rays_time = rays_start.elapsed() / ITERATIONS;
+
+        let shadowcast_start = Instant::now();
+        let mut shadowcast_tiles = HashSet::new();
+        for _ in 0..ITERATIONS {
+            shadowcast_tiles = self.field_of_view(
+                player.pos,
+                player.facing(),
+                player.vision_fov(),
+                player.vision_length(),
+            );
+        }
+        let shadowcast_time = shadowcast_start.elapsed() / ITERATIONS;
+
+        println!("FOV benchmark ({ITERATIONS} iterations)");
+        println!("  Rays: {rays_time:?} per call, {} tiles", ray_tiles.len());
+        println!(
+            "  Shadowcasting: {shadowcast_time:?} per call, {} tiles",
+            shadowcast_tiles.len()
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use crate::items::Item;
+
+#[derive(Clone, PartialEq)]
+pub struct ItemStack {
+    pub item: Item::Kind,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(item: Item::Kind) -> Self {
+        Self { item, count: 1 }
+    }
+
+    pub fn with_count(item: Item::Kind, count: u32) -> Self {
+        Self { item, count }
+    }
+}
+
+// Every place an item can be in an inventory
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InventorySlot {
+    Grid(usize),
+    Primary,
+    Secondary,
+    Clothes,
+    Backpack,
+    Throwable,
+}
+
+// How weighed down the player is by what they carry
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encumbrance {
+    Light,
+    Medium,
+    Heavy,
+    Overloaded,
+}
+
+impl Encumbrance {
+    pub fn from_weight(weight: f32) -> Encumbrance {
+        match weight {
+            w if w < 20.0 => Encumbrance::Light,
+            w if w < 30.0 => Encumbrance::Medium,
+            w if w < 40.0 => Encumbrance::Heavy,
+            _ => Encumbrance::Overloaded,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encumbrance::Light => "Light",
+            Encumbrance::Medium => "Medium",
+            Encumbrance::Heavy => "Heavy",
+            Encumbrance::Overloaded => "Overloaded",
+        }
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        match self {
+            Encumbrance::Light => 1.0,
+            Encumbrance::Medium => 0.93,
+            Encumbrance::Heavy => 0.82,
+            Encumbrance::Overloaded => 0.6,
+        }
+    }
+
+    pub fn acceleration_multiplier(&self) -> f32 {
+        match self {
+            Encumbrance::Light => 1.0,
+            Encumbrance::Medium => 0.9,
+            Encumbrance::Heavy => 0.7,
+            Encumbrance::Overloaded => 0.5,
+        }
+    }
+
+    pub fn stamina_multiplier(&self) -> f32 {
+        match self {
+            Encumbrance::Light => 1.0,
+            Encumbrance::Medium => 1.2,
+            Encumbrance::Heavy => 1.6,
+            Encumbrance::Overloaded => 2.5,
+        }
+    }
+
+    pub fn can_sprint(&self) -> bool {
+        *self != Encumbrance::Overloaded
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GunSlot {
+    Primary,
+    Secondary,
+}
+
+#[derive(Clone)]
+pub struct Inventory {
+    pub grid: Vec<Option<ItemStack>>,
+    pub grid_width: usize,
+    pub primary: Option<Item::Gun>,
+    pub secondary: Option<Item::Gun>,
+    pub clothes: Item::Clothes,
+    pub backpack: Option<Item::Backpack>,
+    pub throwable: Option<ItemStack>,
+    pub selected_gun: GunSlot,
+    pub is_open: bool,
+}
+
+impl Inventory {
+    // Grid size when no backpack is equipped
+    pub const POCKETS_SIZE: (usize, usize) = (2, 1);
+
+    pub fn new(clothes: Item::Clothes, backpack: Option<Item::Backpack>) -> Self {
+        let (grid_width, grid_height) = Inventory::grid_size(backpack.as_ref());
+        Self {
+            grid: vec![None; grid_width * grid_height],
+            grid_width,
+            primary: None,
+            secondary: None,
+            clothes,
+            backpack,
+            throwable: None,
+            selected_gun: GunSlot::Primary,
+            is_open: false,
+        }
+    }
+
+    pub fn grid_size(backpack: Option<&Item::Backpack>) -> (usize, usize) {
+        match backpack {
+            Some(backpack) => (backpack.grid_width, backpack.grid_height),
+            None => Inventory::POCKETS_SIZE,
+        }
+    }
+
+    // Gun currently held by the player
+    pub fn gun(&self) -> Option<&Item::Gun> {
+        match self.selected_gun {
+            GunSlot::Primary => self.primary.as_ref(),
+            GunSlot::Secondary => self.secondary.as_ref(),
+        }
+    }
+
+    // Removes one throwable from the throwable slot
+    pub fn take_throwable(&mut self) -> Option<Item::Kind> {
+        let stack = self.throwable.as_mut()?;
+        let item = stack.item.clone();
+        stack.count -= 1;
+        if stack.count == 0 {
+            self.throwable = None;
+        }
+        Some(item)
+    }
+
+    // Total weight of everything carried and equipped in kg
+    pub fn total_weight(&self) -> f32 {
+        let equipped = [
+            self.primary.as_ref().map(|gun| gun.weight),
+            self.secondary.as_ref().map(|gun| gun.weight),
+            Some(self.clothes.weight),
+            self.backpack.as_ref().map(|backpack| backpack.weight),
+        ];
+        let stored = self
+            .throwable
+            .iter()
+            .chain(self.grid.iter().flatten())
+            .map(|stack| stack.item.weight() * stack.count as f32);
+        equipped.into_iter().flatten().chain(stored).sum()
+    }
+
+    pub fn encumbrance(&self) -> Encumbrance {
+        Encumbrance::from_weight(self.total_weight())
+    }
+
+    // Amount of an item in the grid and throwable slot
+    pub fn count(&self, item: &Item::Kind) -> u32 {
+        self.throwable
+            .iter()
+            .chain(self.grid.iter().flatten())
+            .filter(|stack| &stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    // Removes a single item from the grid or throwable slot
+    // Returns false if the inventory doesn't have the item
+    pub fn remove_one(&mut self, item: &Item::Kind) -> bool {
+        let slots = std::iter::once(&mut self.throwable).chain(self.grid.iter_mut());
+        for slot in slots {
+            if let Some(stack) = slot.as_mut().filter(|stack| &stack.item == item) {
+                stack.count -= 1;
+                if stack.count == 0 {
+                    *slot = None;
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    // Adds items to existing stacks first, then to empty cells
+    // Returns whatever did not fit
+    pub fn add_item(&mut self, mut stack: ItemStack) -> Option<ItemStack> {
+        let max_stack = stack.item.max_stack();
+        let existing = self
+            .throwable
+            .iter_mut()
+            .chain(self.grid.iter_mut().flatten());
+        for existing in existing {
+            if existing.item == stack.item && existing.count < max_stack {
+                let moved = stack.count.min(max_stack - existing.count);
+                existing.count += moved;
+                stack.count -= moved;
+                if stack.count == 0 {
+                    return None;
+                }
+            }
+        }
+        for cell in self.grid.iter_mut().filter(|cell| cell.is_none()) {
+            let moved = stack.count.min(max_stack);
+            *cell = Some(ItemStack::with_count(stack.item.clone(), moved));
+            stack.count -= moved;
+            if stack.count == 0 {
+                return None;
+            }
+        }
+        Some(stack)
+    }
+
+    pub fn accepts(&self, slot: InventorySlot, item: &Item::Kind) -> bool {
+        match slot {
+            InventorySlot::Grid(index) => index < self.grid.len(),
+            InventorySlot::Primary | InventorySlot::Secondary => matches!(item, Item::Kind::Gun(_)),
+            InventorySlot::Clothes => matches!(item, Item::Kind::Clothes(_)),
+            InventorySlot::Backpack => matches!(item, Item::Kind::Backpack(_)),
+            InventorySlot::Throwable => matches!(item, Item::Kind::Grenade),
+        }
+    }
+
+    // Returns a copy of what is in the slot
+    pub fn peek(&self, slot: InventorySlot) -> Option<ItemStack> {
+        match slot {
+            InventorySlot::Grid(index) => self.grid.get(index).cloned().flatten(),
+            InventorySlot::Primary => self
+                .primary
+                .clone()
+                .map(Item::Kind::Gun)
+                .map(ItemStack::new),
+            InventorySlot::Secondary => self
+                .secondary
+                .clone()
+                .map(Item::Kind::Gun)
+                .map(ItemStack::new),
+            InventorySlot::Clothes => {
+                Some(ItemStack::new(Item::Kind::Clothes(self.clothes.clone())))
+            }
+            InventorySlot::Backpack => self
+                .backpack
+                .clone()
+                .map(Item::Kind::Backpack)
+                .map(ItemStack::new),
+            InventorySlot::Throwable => self.throwable.clone(),
+        }
+    }
+
+    // Removes and returns what is in the slot
+    // Clothes are always worn, so they can only be swapped and never taken
+    pub fn take(&mut self, slot: InventorySlot) -> Option<ItemStack> {
+        if slot == InventorySlot::Clothes {
+            return None;
+        }
+        let stack = self.peek(slot);
+        self.set(slot, None);
+        if slot == InventorySlot::Backpack && !self.fit_grid_to_backpack() {
+            self.set(slot, stack);
+            return None;
+        }
+        stack
+    }
+
+    // Overwrites the slot, the slot must accept the item
+    fn set(&mut self, slot: InventorySlot, stack: Option<ItemStack>) {
+        let item = stack.as_ref().map(|stack| stack.item.clone());
+        match slot {
+            InventorySlot::Grid(index) => {
+                if let Some(cell) = self.grid.get_mut(index) {
+                    *cell = stack;
+                }
+            }
+            InventorySlot::Primary => {
+                self.primary = match item {
+                    Some(Item::Kind::Gun(gun)) => Some(gun),
+                    _ => None,
+                }
+            }
+            InventorySlot::Secondary => {
+                self.secondary = match item {
+                    Some(Item::Kind::Gun(gun)) => Some(gun),
+                    _ => None,
+                }
+            }
+            InventorySlot::Clothes => {
+                if let Some(Item::Kind::Clothes(clothes)) = item {
+                    self.clothes = clothes;
+                }
+            }
+            InventorySlot::Backpack => {
+                self.backpack = match item {
+                    Some(Item::Kind::Backpack(backpack)) => Some(backpack),
+                    _ => None,
+                }
+            }
+            InventorySlot::Throwable => self.throwable = stack,
+        }
+    }
+
+    // Moves an item between slots, merging stacks or swapping with what was already there
+    // Returns false if the move was not possible and nothing changed
+    pub fn move_item(&mut self, from: InventorySlot, to: InventorySlot) -> bool {
+        if from == to {
+            return false;
+        }
+        let (moving, target) = match (self.peek(from), self.peek(to)) {
+            (Some(moving), target) => (moving, target),
+            (None, _) => return false,
+        };
+        if !self.accepts(to, &moving.item) {
+            return false;
+        }
+
+        // Merge into a stack of the same item
+        if let Some(target) = &target {
+            let max_stack = target.item.max_stack();
+            if target.item == moving.item && target.count < max_stack {
+                let moved = moving.count.min(max_stack - target.count);
+                let remaining = moving.count - moved;
+                self.set(
+                    to,
+                    Some(ItemStack::with_count(
+                        target.item.clone(),
+                        target.count + moved,
+                    )),
+                );
+                self.set(
+                    from,
+                    (remaining > 0).then(|| ItemStack::with_count(moving.item.clone(), remaining)),
+                );
+                return true;
+            }
+        }
+
+        // Swap, as long as whatever was in the target slot can go where the item came from
+        match &target {
+            Some(target) if !self.accepts(from, &target.item) => return false,
+            None if from == InventorySlot::Clothes => return false,
+            _ => {}
+        }
+        let before = self.clone();
+        self.set(to, Some(moving));
+        self.set(from, target);
+
+        // Changing backpacks resizes the grid, undo the move if the items no longer fit
+        if !self.fit_grid_to_backpack() {
+            *self = before;
+            return false;
+        }
+        true
+    }
+
+    // Resizes the grid to the equipped backpack, keeping items in place where possible
+    // Returns false and leaves the grid untouched if the items do not fit
+    fn fit_grid_to_backpack(&mut self) -> bool {
+        let (width, height) = Inventory::grid_size(self.backpack.as_ref());
+        if width == self.grid_width && width * height == self.grid.len() {
+            return true;
+        }
+
+        let mut grid: Vec<Option<ItemStack>> = vec![None; width * height];
+        let mut displaced = vec![];
+        for (index, stack) in self.grid.iter().enumerate() {
+            let Some(stack) = stack else { continue };
+            let (x, y) = (index % self.grid_width, index / self.grid_width);
+            if x < width && y < height {
+                grid[x + y * width] = Some(stack.clone());
+            } else {
+                displaced.push(stack.clone());
+            }
+        }
+        for stack in displaced {
+            match grid.iter_mut().find(|cell| cell.is_none()) {
+                Some(cell) => *cell = Some(stack),
+                None => return false,
+            }
+        }
+
+        self.grid = grid;
+        self.grid_width = width;
+        true
+    }
+}
@@ -1,22 +1,159 @@
 #[allow(non_upper_case_globals, non_snake_case)]
 pub mod Item {
+    use crate::{manifest::TextureId, player::PlayerStance, status_effects::StatusEffectKind};
     use macroquad::prelude::Vec2;
 
-    #[derive(PartialEq)]
+    #[derive(Clone, PartialEq)]
     pub struct Gun {
         pub name: &'static str,
         pub bullet_speed: f32,
         pub bullet_spread: f32,
         pub bullets_per_shot: u32,
+        pub bullet_damage: f32,
+        pub penetration: f32, // Power bullets have for passing through tiles like wood and glass
         pub barrel_offset: Vec2,
+        pub weight: f32,
     }
 
+    #[derive(Clone, PartialEq)]
     pub struct Clothes {
         pub name: &'static str,
+        pub color: &'static str, // Colors the player bitmap is recolored with, see Assets::load_clothes
+        pub damage_reduction: f32, // Fraction of damage absorbed at full durability
+        pub durability: f32,
+        pub max_durability: f32,
+        pub movement_penalty: f32, // Fraction of max speed lost
+        pub stamina_penalty: f32,  // Fraction of extra stamina used when sprinting
+        pub weight: f32,
     }
 
+    #[derive(Clone, PartialEq)]
+    pub struct Ammo {
+        pub name: &'static str,
+        pub max_stack: u32,
+        pub weight: f32, // Weight of a single round
+    }
+
+    #[derive(Clone, PartialEq)]
+    pub struct Consumable {
+        pub name: &'static str,
+        pub use_time: f32, // Seconds it takes to use
+        pub heal: f32,
+        pub stops_bleeding: bool,
+        pub effect: Option<(StatusEffectKind, f32)>, // Effect given and its duration
+        pub max_stack: u32,
+        pub weight: f32,
+    }
+
+    // Opens every locked door with the same key name
+    #[derive(Clone, PartialEq)]
+    pub struct Key {
+        pub name: &'static str,
+        pub weight: f32,
+    }
+
+    #[derive(Clone, PartialEq)]
     pub struct Backpack {
         pub name: &'static str,
+        pub grid_width: usize,
+        pub grid_height: usize,
+        pub weight: f32,
+    }
+
+    // Anything that can be stored in an inventory
+    #[derive(Clone, PartialEq)]
+    pub enum Kind {
+        Gun(Gun),
+        Clothes(Clothes),
+        Backpack(Backpack),
+        Ammo(Ammo),
+        Consumable(Consumable),
+        Key(Key),
+        Grenade,
+    }
+
+    impl Kind {
+        // Every item in the game, lookups by name and the asset manifest are derived from this
+        pub fn all() -> Vec<Kind> {
+            vec![
+                Kind::Gun(Gun::sawed_shotgun()),
+                Kind::Gun(Gun::sniper()),
+                Kind::Clothes(Clothes::blue_clothes()),
+                Kind::Clothes(Clothes::dark_clothes()),
+                Kind::Clothes(Clothes::red_clothes()),
+                Kind::Clothes(Clothes::light_armor()),
+                Kind::Clothes(Clothes::heavy_armor()),
+                Kind::Backpack(Backpack::black_backpack()),
+                Kind::Backpack(Backpack::brown_backpack()),
+                Kind::Ammo(Ammo::shotgun_shells()),
+                Kind::Ammo(Ammo::sniper_rounds()),
+                Kind::Consumable(Consumable::bandage()),
+                Kind::Consumable(Consumable::medkit()),
+                Kind::Consumable(Consumable::stimulant()),
+                Kind::Key(Key::storage_key()),
+                Kind::Grenade,
+            ]
+        }
+
+        // Looks up an item by the name used in data files
+        pub fn from_name(name: &str) -> Option<Kind> {
+            Kind::all().into_iter().find(|kind| kind.name() == name)
+        }
+
+        pub fn name(&self) -> &'static str {
+            match self {
+                Kind::Gun(gun) => gun.name,
+                Kind::Clothes(clothes) => clothes.name,
+                Kind::Backpack(backpack) => backpack.name,
+                Kind::Ammo(ammo) => ammo.name,
+                Kind::Consumable(consumable) => consumable.name,
+                Kind::Key(key) => key.name,
+                Kind::Grenade => "grenade",
+            }
+        }
+
+        // Weight of a single item in kg
+        pub fn weight(&self) -> f32 {
+            match self {
+                Kind::Gun(gun) => gun.weight,
+                Kind::Clothes(clothes) => clothes.weight,
+                Kind::Backpack(backpack) => backpack.weight,
+                Kind::Ammo(ammo) => ammo.weight,
+                Kind::Consumable(consumable) => consumable.weight,
+                Kind::Key(key) => key.weight,
+                Kind::Grenade => 0.4,
+            }
+        }
+
+        // How many of the item fit in a single inventory cell
+        pub fn max_stack(&self) -> u32 {
+            match self {
+                Kind::Ammo(ammo) => ammo.max_stack,
+                Kind::Consumable(consumable) => consumable.max_stack,
+                Kind::Grenade => 3,
+                _ => 1,
+            }
+        }
+
+        // Texture the item is drawn with in inventories and on the ground
+        pub fn texture(&self) -> TextureId {
+            match self {
+                Kind::Gun(gun) => TextureId::Gun {
+                    name: gun.name,
+                    aiming: false,
+                },
+                Kind::Clothes(clothes) => TextureId::Clothes {
+                    color: clothes.color,
+                    stance: PlayerStance::Standing,
+                    aiming: false,
+                },
+                Kind::Backpack(backpack) => TextureId::Backpack(backpack.name),
+                Kind::Ammo(ammo) => TextureId::ItemIcon(ammo.name),
+                Kind::Consumable(consumable) => TextureId::ItemIcon(consumable.name),
+                Kind::Key(key) => TextureId::ItemIcon(key.name),
+                Kind::Grenade => TextureId::GrenadePinned,
+            }
+        }
     }
 
     // Guns
@@ -27,7 +164,10 @@ pub mod Item {
                 bullet_speed: 6.5,
                 bullet_spread: 0.120,
                 bullets_per_shot: 10,
+                bullet_damage: 9.0,
+                penetration: 0.1,
                 barrel_offset: Vec2::new(1.0, -0.0),
+                weight: 3.2,
             }
         }
 
@@ -37,21 +177,144 @@ pub mod Item {
                 bullet_speed: 9.0,
                 bullet_spread: 0.01,
                 bullets_per_shot: 1,
+                bullet_damage: 75.0,
+                penetration: 1.5,
                 barrel_offset: Vec2::new(1.0, -0.0),
+                weight: 6.5,
             }
         }
     }
 
     impl Clothes {
+        // Armor loses this much durability per point of damage it absorbs
+        const DURABILITY_LOSS: f32 = 1.5;
+
+        fn cosmetic(name: &'static str, color: &'static str) -> Clothes {
+            Clothes {
+                name,
+                color,
+                damage_reduction: 0.0,
+                durability: 0.0,
+                max_durability: 0.0,
+                movement_penalty: 0.0,
+                stamina_penalty: 0.0,
+                weight: 1.0,
+            }
+        }
+
         // Clothes
         pub fn blue_clothes() -> Clothes {
-            Clothes { name: "blue" }
+            Clothes::cosmetic("blue_clothes", "blue")
         }
         pub fn dark_clothes() -> Clothes {
-            Clothes { name: "dark" }
+            Clothes::cosmetic("dark_clothes", "dark")
         }
         pub fn red_clothes() -> Clothes {
-            Clothes { name: "red" }
+            Clothes::cosmetic("red_clothes", "red")
+        }
+
+        // Armor
+        pub fn light_armor() -> Clothes {
+            Clothes {
+                name: "light_armor",
+                color: "light_armor",
+                damage_reduction: 0.3,
+                durability: 60.0,
+                max_durability: 60.0,
+                movement_penalty: 0.05,
+                stamina_penalty: 0.1,
+                weight: 6.0,
+            }
+        }
+        pub fn heavy_armor() -> Clothes {
+            Clothes {
+                name: "heavy_armor",
+                color: "heavy_armor",
+                damage_reduction: 0.55,
+                durability: 120.0,
+                max_durability: 120.0,
+                movement_penalty: 0.15,
+                stamina_penalty: 0.35,
+                weight: 12.0,
+            }
+        }
+
+        // Damage reduction scales down as the armor wears out
+        pub fn effective_damage_reduction(&self) -> f32 {
+            if self.max_durability <= 0.0 {
+                return 0.0;
+            }
+            self.damage_reduction * (self.durability / self.max_durability)
+        }
+
+        // Absorbs part of the damage, wearing down the armor
+        // Returns the damage that goes through to the player
+        pub fn absorb(&mut self, damage: f32) -> f32 {
+            let absorbed = damage * self.effective_damage_reduction();
+            self.durability = (self.durability - absorbed * Clothes::DURABILITY_LOSS).max(0.0);
+            damage - absorbed
+        }
+    }
+
+    impl Ammo {
+        pub fn shotgun_shells() -> Ammo {
+            Ammo {
+                name: "shotgun_shells",
+                max_stack: 20,
+                weight: 0.05,
+            }
+        }
+        pub fn sniper_rounds() -> Ammo {
+            Ammo {
+                name: "sniper_rounds",
+                max_stack: 10,
+                weight: 0.03,
+            }
+        }
+    }
+
+    impl Consumable {
+        pub fn bandage() -> Consumable {
+            Consumable {
+                name: "bandage",
+                use_time: 2.0,
+                heal: 5.0,
+                stops_bleeding: true,
+                effect: None,
+                max_stack: 5,
+                weight: 0.1,
+            }
+        }
+        pub fn medkit() -> Consumable {
+            Consumable {
+                name: "medkit",
+                use_time: 5.0,
+                heal: 60.0,
+                stops_bleeding: true,
+                effect: None,
+                max_stack: 2,
+                weight: 0.8,
+            }
+        }
+        pub fn stimulant() -> Consumable {
+            Consumable {
+                name: "stimulant",
+                use_time: 1.0,
+                heal: 0.0,
+                stops_bleeding: false,
+                effect: Some((StatusEffectKind::Stimulated, 30.0)),
+                max_stack: 3,
+                weight: 0.1,
+            }
+        }
+    }
+
+    impl Key {
+        pub fn storage_key() -> Key {
+            Key {
+                name: "storage_key",
+                weight: 0.05,
+            }
         }
     }
 
@@ -59,12 +322,33 @@ pub mod Item {
         pub fn black_backpack() -> Backpack {
             Backpack {
                 name: "black_backpack",
+                grid_width: 4,
+                grid_height: 3,
+                weight: 1.5,
             }
         }
         pub fn brown_backpack() -> Backpack {
             Backpack {
                 name: "brown_backpack",
+                grid_width: 5,
+                grid_height: 4,
+                weight: 2.0,
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Item::Kind;
+    use std::collections::HashSet;
+
+    #[test]
+    fn item_names_are_unique() {
+        let names: HashSet<&str> = Kind::all().iter().map(Kind::name).collect();
+        assert_eq!(names.len(), Kind::all().len());
+        for name in names {
+            assert!(Kind::from_name(name).is_some_and(|kind| kind.name() == name));
+        }
+    }
+}
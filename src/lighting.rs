@@ -0,0 +1,302 @@
+use crate::{camera::GameCamera, explosion::Explosion, shadows::VERTEX_SHADER, tile_map::TileMap};
+use macroquad::{models::Vertex, prelude::*};
+use miniquad::{BlendFactor, BlendState, BlendValue, Equation};
+use std::collections::HashMap;
+
+// Light shining out from a point, walls cast shadows in it
+pub struct Light {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub color: Color,
+    pub intensity: f32, // Past 1.0 the light brightens things past their normal color
+    pub cone: Option<(Vec2, f32)>, // Direction and angle, for lights that only shine one way
+}
+
+// Lamp placed on the map
+pub struct Lamp {
+    pub pos: Vec2,
+    pub color: Color,
+}
+
+impl Lamp {
+    const RADIUS: f32 = 8.0 * 9.0;
+
+    pub fn new(grid_x: u16, grid_y: u16, color: Color) -> Self {
+        Self {
+            pos: Vec2::new(grid_x as f32 * 8.0 + 4.0, grid_y as f32 * 8.0 + 4.0),
+            color,
+        }
+    }
+
+    pub fn light(&self) -> Light {
+        Light {
+            pos: self.pos,
+            radius: Lamp::RADIUS,
+            color: self.color,
+            intensity: 1.2,
+            cone: None,
+        }
+    }
+
+    pub fn draw(&self) {
+        draw_circle(
+            self.pos.x,
+            self.pos.y,
+            1.6,
+            Color::from_rgba(40, 40, 40, 255),
+        );
+        draw_circle(self.pos.x, self.pos.y, 1.1, self.color);
+    }
+}
+
+// Short burst of light, from muzzle flashes and explosions
+pub struct Flash {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub color: Color,
+    pub intensity: f32,
+    pub time_left: f32,
+    duration: f32,
+}
+
+impl Flash {
+    pub fn muzzle(pos: Vec2) -> Flash {
+        Flash {
+            pos,
+            radius: 8.0 * 6.0,
+            color: Color::new(1.0, 0.85, 0.55, 1.0),
+            intensity: 1.6,
+            time_left: 0.06,
+            duration: 0.06,
+        }
+    }
+
+    pub fn explosion(explosion: &Explosion) -> Flash {
+        Flash {
+            pos: explosion.pos,
+            radius: explosion.radius * 2.5,
+            color: Color::new(1.0, 0.65, 0.3, 1.0),
+            intensity: 2.0,
+            time_left: 0.35,
+            duration: 0.35,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.time_left -= get_frame_time();
+    }
+
+    // Fades out over the duration of the flash
+    pub fn light(&self) -> Light {
+        Light {
+            pos: self.pos,
+            radius: self.radius,
+            color: self.color,
+            intensity: self.intensity * (self.time_left / self.duration).max(0.0),
+            cone: None,
+        }
+    }
+}
+
+// Lights are added up into a light map on top of the map's ambient light
+// The light map is then multiplied over everything drawn in the world
+pub struct LightRenderer {
+    light_map: RenderTarget,
+    add_material: Material,
+    multiply_material: Material,
+    // Outlines of lights without a cone, by position and radius bits
+    // Lamps never move, so their outlines only change when the tile map does
+    outlines: HashMap<(u32, u32, u32), Vec<Vec2>>,
+    outlines_revision: u32,
+}
+
+impl LightRenderer {
+    const LIGHT_MAP_DOWNSCALE: f32 = 4.0;
+    // The light map holds half of the light, and is doubled when it's multiplied over the world
+    // That way lights are able to brighten things up to twice their normal color
+    const LIGHT_SCALE: f32 = 0.5;
+
+    pub fn new() -> Self {
+        let load = |color_blend: BlendState| {
+            load_material(
+                ShaderSource::Glsl {
+                    vertex: VERTEX_SHADER,
+                    fragment: LIGHT_FRAGMENT_SHADER,
+                },
+                MaterialParams {
+                    pipeline_params: PipelineParams {
+                        color_blend: Some(color_blend),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        Self {
+            light_map: LightRenderer::light_map(),
+            add_material: load(BlendState::new(
+                Equation::Add,
+                BlendFactor::One,
+                BlendFactor::One,
+            )),
+            // Source times destination, twice
+            multiply_material: load(BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::DestinationColor),
+                BlendFactor::Value(BlendValue::SourceColor),
+            )),
+            outlines: HashMap::new(),
+            outlines_revision: 0,
+        }
+    }
+
+    fn light_map_size() -> (u32, u32) {
+        (
+            (screen_width() / LightRenderer::LIGHT_MAP_DOWNSCALE).ceil() as u32,
+            (screen_height() / LightRenderer::LIGHT_MAP_DOWNSCALE).ceil() as u32,
+        )
+    }
+
+    fn light_map() -> RenderTarget {
+        let (width, height) = LightRenderer::light_map_size();
+        let light_map = render_target(width, height);
+        light_map.texture.set_filter(FilterMode::Linear);
+        light_map
+    }
+
+    // The light map follows the size of the window
+    fn resize(&mut self) {
+        let (width, height) = LightRenderer::light_map_size();
+        if self.light_map.texture.width() as u32 == width
+            && self.light_map.texture.height() as u32 == height
+        {
+            return;
+        }
+        self.light_map.delete();
+        self.light_map = LightRenderer::light_map();
+    }
+
+    pub fn update_light_map(
+        &mut self,
+        tile_map: &TileMap,
+        camera: &GameCamera,
+        ambient: Color,
+        lights: &[Light],
+    ) {
+        self.resize();
+        set_camera(&camera.with_render_target(&self.light_map));
+        clear_background(Color::new(
+            ambient.r * LightRenderer::LIGHT_SCALE,
+            ambient.g * LightRenderer::LIGHT_SCALE,
+            ambient.b * LightRenderer::LIGHT_SCALE,
+            1.0,
+        ));
+
+        // Lights that can't reach the screen are skipped
+        let top_left = camera.screen_to_world(Vec2::ZERO);
+        let bottom_right = camera.screen_to_world(Vec2::new(screen_width(), screen_height()));
+        let (view_min, view_max) = (top_left.min(bottom_right), top_left.max(bottom_right));
+
+        if self.outlines_revision != tile_map.revision {
+            self.outlines.clear();
+            self.outlines_revision = tile_map.revision;
+        }
+        let mut outlines = HashMap::new();
+
+        gl_use_material(&self.add_material);
+        for light in lights {
+            let closest = light.pos.clamp(view_min, view_max);
+            if closest.distance(light.pos) >= light.radius {
+                continue;
+            }
+            let mesh = match light.cone {
+                Some((direction, angle)) => {
+                    let outline =
+                        tile_map.visibility_polygon(light.pos, direction, angle, light.radius);
+                    LightRenderer::light_mesh(light, &outline)
+                }
+                None => {
+                    let key = (
+                        light.pos.x.to_bits(),
+                        light.pos.y.to_bits(),
+                        light.radius.to_bits(),
+                    );
+                    let outline = self.outlines.remove(&key).unwrap_or_else(|| {
+                        tile_map.visibility_polygon(
+                            light.pos,
+                            Vec2::X,
+                            std::f32::consts::TAU,
+                            light.radius,
+                        )
+                    });
+                    LightRenderer::light_mesh(light, outlines.entry(key).or_insert(outline))
+                }
+            };
+            draw_mesh(&mesh);
+        }
+        gl_use_default_material();
+
+        // Only lights drawn this frame stay cached, so short flashes don't pile up
+        self.outlines = outlines;
+    }
+
+    // Fan of triangles covering the area the light reaches, fading out towards its radius
+    fn light_mesh(light: &Light, outline: &[Vec2]) -> Mesh {
+        let vertex = |pos: Vec2| {
+            let falloff = (1.0 - pos.distance(light.pos) / light.radius).max(0.0);
+            let brightness = light.intensity * falloff * LightRenderer::LIGHT_SCALE;
+            Vertex {
+                position: pos.extend(0.0),
+                uv: Vec2::ZERO,
+                color: Color::new(
+                    light.color.r * brightness,
+                    light.color.g * brightness,
+                    light.color.b * brightness,
+                    1.0,
+                ),
+            }
+        };
+        let vertices = std::iter::once(light.pos)
+            .chain(outline.iter().copied())
+            .map(vertex)
+            .collect();
+        let indices = (1..outline.len() as u16)
+            .flat_map(|i| [0, i, i + 1])
+            .collect();
+        Mesh {
+            vertices,
+            indices,
+            texture: None,
+        }
+    }
+
+    // Multiplies the light map over everything drawn in the world so far
+    pub fn draw_lighting(&self, camera: &GameCamera) {
+        set_default_camera();
+        gl_use_material(&self.multiply_material);
+        draw_texture_ex(
+            &self.light_map.texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(screen_width(), screen_height())),
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+        set_camera(camera);
+    }
+}
+
+const LIGHT_FRAGMENT_SHADER: &str = "#version 100
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = color * texture2D(Texture, uv);
+}
+";
@@ -0,0 +1,199 @@
+use crate::{inventory::ItemStack, items::Item, utils::Rng};
+use macroquad::prelude::*;
+
+pub struct LootEntry {
+    pub weight: u32,
+    pub item: Item::Kind,
+    pub min_count: u32,
+    pub max_count: u32,
+}
+
+// Weighted list of items, loaded from the files in assets/loot
+pub struct LootTable {
+    pub min_rolls: u32,
+    pub max_rolls: u32,
+    pub entries: Vec<LootEntry>,
+}
+
+impl LootTable {
+    // Each line is either "rolls <min> <max>" or "<weight> <item> <min count> <max count>"
+    // Empty lines and lines starting with # are ignored
+    pub fn parse(source: &str) -> Result<LootTable, String> {
+        let mut table = LootTable {
+            min_rolls: 1,
+            max_rolls: 1,
+            entries: vec![],
+        };
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |reason: &str| format!("line {}: {reason} ({line})", line_number + 1);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let number = |index: usize| -> Result<u32, String> {
+                words
+                    .get(index)
+                    .ok_or_else(|| error("missing value"))?
+                    .parse()
+                    .map_err(|_| error("expected a number"))
+            };
+
+            if words[0] == "rolls" {
+                table.min_rolls = number(1)?;
+                table.max_rolls = number(2)?;
+                continue;
+            }
+            let item_name = words.get(1).ok_or_else(|| error("missing item"))?;
+            table.entries.push(LootEntry {
+                weight: number(0)?,
+                item: Item::Kind::from_name(item_name).ok_or_else(|| error("unknown item"))?,
+                min_count: number(2)?,
+                max_count: number(3)?,
+            });
+        }
+        Ok(table)
+    }
+
+    pub fn load(path: &str) -> LootTable {
+        let source = std::fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("Failed to read loot table '{path}': {err}"));
+        LootTable::parse(&source).unwrap_or_else(|err| panic!("Invalid loot table '{path}': {err}"))
+    }
+
+    pub fn roll(&self, rng: &mut Rng) -> Vec<ItemStack> {
+        let total_weight: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        if total_weight == 0 {
+            return vec![];
+        }
+
+        let rolls = rng.gen_range_u32(self.min_rolls, self.max_rolls);
+        let mut items = vec![];
+        for _ in 0..rolls {
+            let mut pick = rng.gen_range_u32(0, total_weight - 1);
+            for entry in &self.entries {
+                if pick < entry.weight {
+                    let count = rng.gen_range_u32(entry.min_count, entry.max_count);
+                    items.push(ItemStack::with_count(entry.item.clone(), count.max(1)));
+                    break;
+                }
+                pick -= entry.weight;
+            }
+        }
+        items
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    Crate,
+    Locker,
+}
+
+impl ContainerKind {
+    pub const ALL: [ContainerKind; 2] = [ContainerKind::Crate, ContainerKind::Locker];
+
+    pub fn loot_table_path(&self) -> &'static str {
+        match self {
+            ContainerKind::Crate => "assets/loot/crate.txt",
+            ContainerKind::Locker => "assets/loot/locker.txt",
+        }
+    }
+}
+
+// Lootable object placed on the map, its items are revealed one by one while searched
+pub struct Container {
+    pub pos: Vec2,
+    pub kind: ContainerKind,
+    pub items: Vec<Option<ItemStack>>,
+    pub search_time: f32,
+}
+
+impl Container {
+    pub const SEARCH_TIME_PER_ITEM: f32 = 0.8;
+    pub const INTERACT_RANGE: f32 = 12.0;
+
+    pub fn new(grid_x: u16, grid_y: u16, kind: ContainerKind, items: Vec<ItemStack>) -> Self {
+        Self {
+            pos: Vec2::new(grid_x as f32 * 8.0 + 4.0, grid_y as f32 * 8.0 + 4.0),
+            kind,
+            items: items.into_iter().map(Some).collect(),
+            search_time: 0.0,
+        }
+    }
+
+    // Amount of items the player has found so far
+    pub fn revealed(&self) -> usize {
+        ((self.search_time / Container::SEARCH_TIME_PER_ITEM) as usize).min(self.items.len())
+    }
+
+    pub fn is_fully_searched(&self) -> bool {
+        self.revealed() == self.items.len()
+    }
+
+    pub fn search(&mut self, delta: f32) {
+        if !self.is_fully_searched() {
+            self.search_time += delta;
+        }
+    }
+
+    // Progress towards revealing the next item, from 0.0 to 1.0
+    pub fn search_progress(&self) -> f32 {
+        match self.is_fully_searched() {
+            true => 1.0,
+            false => (self.search_time / Container::SEARCH_TIME_PER_ITEM).fract(),
+        }
+    }
+
+    // Takes a revealed item out of the container
+    pub fn take(&mut self, index: usize) -> Option<ItemStack> {
+        if index >= self.revealed() {
+            return None;
+        }
+        self.items.get_mut(index)?.take()
+    }
+
+    pub fn draw(&self) {
+        let (size, color) = match self.kind {
+            ContainerKind::Crate => (Vec2::new(7.0, 7.0), Color::from_rgba(120, 82, 45, 255)),
+            ContainerKind::Locker => (Vec2::new(6.0, 7.5), Color::from_rgba(95, 105, 115, 255)),
+        };
+        let rect = Rect::new(
+            self.pos.x - size.x / 2.0,
+            self.pos.y - size.y / 2.0,
+            size.x,
+            size.y,
+        );
+        draw_rectangle(
+            rect.x + 0.5,
+            rect.y + 0.5,
+            rect.w,
+            rect.h,
+            Color::from_rgba(0, 0, 0, 70),
+        );
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 0.5, BLACK);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_loot_tables_parse() {
+        for kind in ContainerKind::ALL {
+            let path = kind.loot_table_path();
+            let source = std::fs::read_to_string(path).unwrap();
+            let table = LootTable::parse(&source).unwrap_or_else(|err| panic!("{path}: {err}"));
+            assert!(!table.entries.is_empty(), "{path} has no entries");
+        }
+    }
+
+    #[test]
+    fn unknown_items_are_rejected() {
+        let err = LootTable::parse("rolls 1 2\n10 blue 1 1").err().unwrap();
+        assert_eq!(err, "line 2: unknown item (10 blue 1 1)");
+    }
+}
@@ -1,31 +1,65 @@
 use macroquad::prelude::*;
 use assets::Assets;
+use audio::AudioBackend;
 use camera::GameCamera;
+use console::DebugConsole;
 use entities::Grenade;
+use manifest::{asset_files, AssetManifest, VALIDATE_ASSETS_FLAG};
 use player::*;
+use save::*;
 use ui::*;
 use utils::conf;
 use world::World;
 
 mod assets;
+mod audio;
 mod camera;
+mod console;
+mod doors;
 mod entities;
+mod environment;
+mod explosion;
+mod fov;
+mod inventory;
 mod items;
+mod lighting;
+mod loot;
+mod manifest;
 mod maps;
+mod materials;
+mod memory;
+mod music;
+mod noise;
 mod player;
+mod save;
+mod shadows;
+mod status_effects;
 mod tile_map;
 mod ui;
 mod utils;
 mod world;
 
-#[macroquad::main(conf)]
-async fn main() {
+fn main() {
+    // Only checks the assets, exiting with an error code if any are missing
+    // Checked before the window is created, so it also works without a display
+    if std::env::args().any(|arg| arg == VALIDATE_ASSETS_FLAG) {
+        let report = AssetManifest::expected().validate(&asset_files());
+        report.print();
+        std::process::exit(report.has_errors() as i32);
+    }
+    macroquad::Window::from_config(conf(), run());
+}
+
+async fn run() {
     let mut camera = GameCamera::new();
-    let assets = Assets::new().await;
+    let assets = Assets::new(AudioBackend::from_args()).await;
     let mut fps_graph = FpsBarGraph::new();
+    let mut inventory_screen = InventoryScreen::new();
+    let mut audio_settings_screen = AudioSettingsScreen::new();
     let mut player = Player::new(52, 55);
     let mut world = World::new();
     let mut debug_on = false;
+    let mut console = DebugConsole::new();
 
     player.controller = PlayerController::User; // Allow control from the user
     camera.target = player.pos; // Teleport camera to player
@@ -33,16 +67,59 @@ async fn main() {
 
     // Main game loop
     loop {
+        player.input_blocked = console.is_open;
         player.update(&camera, &world.tile_map);
-        world.update(&player, &camera, &assets).await;
+        world
+            .update(&mut player, &mut camera, &assets, console.is_open)
+            .await;
         camera.handle_controls();
         camera.pan_to_target(player.pos);
+        if !console.is_open {
+            match inventory_screen.update(&mut player.inventory) {
+                Some(InventoryAction::Drop(dropped)) => world.entities.drop_item(&player, dropped),
+                Some(InventoryAction::Use(consumable)) => player.start_using(consumable),
+                None => {}
+            }
+            audio_settings_screen.update(&assets.sounds.mixer);
+        }
+        if let Some(index) = world.entities.looting {
+            if let Some(item_index) = ContainerScreen::update(&world.entities.containers[index]) {
+                world.entities.loot_item(&mut player, item_index);
+            }
+        }
 
         if is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::T) {
             debug_on = !debug_on;
         }
+        if debug_on && is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::B) {
+            world.tile_map.benchmark_fov(&player);
+        }
+        if debug_on {
+            if let Some(command) = console.update() {
+                console.print(world.run_command(&command, &assets));
+            }
+        } else {
+            console.is_open = false;
+        }
+
+        if is_key_pressed(SAVE_KEY) && !console.is_open {
+            match SaveGame::save(SAVE_PATH, &player) {
+                Ok(()) => println!("Saved game to '{SAVE_PATH}'"),
+                Err(err) => println!("{err}"),
+            }
+        }
+        if is_key_pressed(LOAD_KEY) && !console.is_open {
+            match SaveGame::load(SAVE_PATH, &mut player) {
+                Ok(()) => camera.target = player.pos,
+                Err(err) => println!("{err}"),
+            }
+        }
 
-        if is_key_pressed(KeyCode::G) {
+        if is_key_pressed(KeyCode::G)
+            && !player.inventory.is_open
+            && !console.is_open
+            && player.inventory.take_throwable().is_some()
+        {
             world.entities.grenades.push(Grenade {
                 pos: camera.screen_to_world(mouse_position().into()),
                 fuse_time: Grenade::MAX_FUSE_TIME,
@@ -58,8 +135,6 @@ async fn main() {
         set_camera(&camera);
 
         world.draw(&camera, &player, &assets);
-        // Draw player
-        player.draw(&assets);
 
         // Draw debug thingys
         if debug_on {
@@ -72,9 +147,26 @@ async fn main() {
 
         // Rendering UI
         render_ui(&player);
+        render_sound_indicators(&player, &camera);
+        if let Some(index) = world.entities.looting {
+            ContainerScreen::draw(&world.entities.containers[index], &assets);
+        } else if let Some(index) = world.entities.nearest_pickup(&player, &world.visible_tiles) {
+            render_pickup_prompt(world.entities.ground_items[index].stack.item.name());
+        } else if world
+            .entities
+            .nearest_container(&player, &world.visible_tiles)
+            .is_some()
+        {
+            render_interact_prompt("Search");
+        } else if let Some(index) = world.entities.nearest_door(&player) {
+            render_interact_prompt(world.entities.doors[index].action_name(&player));
+        }
+        inventory_screen.draw(&player.inventory, &assets);
+        audio_settings_screen.draw(&assets.sounds.mixer);
         if debug_on {
             render_debug_ui(&player, &camera, &world.tile_map);
             fps_graph.draw();
+            console.draw();
         }
 
         next_frame().await;
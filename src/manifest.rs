@@ -0,0 +1,324 @@
+use crate::{
+    assets::{PLAYER_AIMING_BITMAP, PLAYER_IDLE_BITMAP},
+    items::Item,
+    loot::{ContainerKind, LootTable},
+    maps,
+    music::{MusicState, MUSIC_DIR},
+    player::PlayerStance,
+};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+pub const ASSETS_DIR: &str = "assets";
+pub const VALIDATE_ASSETS_FLAG: &str = "--validate-assets";
+
+// Scratch files that aren't part of the game
+const IGNORED_DIR: &str = "temp";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextureId {
+    Tiles,
+    Gun {
+        name: &'static str,
+        aiming: bool,
+    },
+    Clothes {
+        color: &'static str,
+        stance: PlayerStance,
+        aiming: bool,
+    },
+    Backpack(&'static str),
+    ItemIcon(&'static str), // Items that are only ever drawn as an icon
+    GrenadePinned,
+    GrenadeUnpinned,
+}
+
+impl TextureId {
+    pub fn file_name(&self) -> String {
+        match self {
+            TextureId::Tiles => "tiles.png".to_string(),
+            TextureId::Gun { name, aiming } => format!("{name}_{}.png", pose(*aiming)),
+            TextureId::Clothes {
+                color,
+                stance,
+                aiming,
+            } => format!(
+                "{color}_clothes{}_{}.png",
+                stance.texture_suffix(),
+                pose(*aiming)
+            ),
+            TextureId::Backpack(name) | TextureId::ItemIcon(name) => format!("{name}.png"),
+            TextureId::GrenadePinned => "grenade_pinned.png".to_string(),
+            TextureId::GrenadeUnpinned => "grenade_unpinned.png".to_string(),
+        }
+    }
+
+    // Clothes are generated from the player bitmaps when loading instead of read from a file
+    fn is_generated(&self) -> bool {
+        matches!(self, TextureId::Clothes { .. })
+    }
+}
+
+fn pose(aiming: bool) -> &'static str {
+    match aiming {
+        true => "aiming",
+        false => "idle",
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SoundId {
+    GunShot(&'static str), // Name of the gun
+    GrenadeExplosion,
+    Ricochet,
+    ImpactConcrete,
+    ImpactWood,
+    ImpactGlass,
+    ImpactMetal,
+    DoorOpen,
+    DoorClose,
+    DoorLocked,
+    Rain,
+}
+
+impl SoundId {
+    // Every sound that doesn't belong to an item
+    const FIXED: [SoundId; 10] = [
+        SoundId::GrenadeExplosion,
+        SoundId::Ricochet,
+        SoundId::ImpactConcrete,
+        SoundId::ImpactWood,
+        SoundId::ImpactGlass,
+        SoundId::ImpactMetal,
+        SoundId::DoorOpen,
+        SoundId::DoorClose,
+        SoundId::DoorLocked,
+        SoundId::Rain,
+    ];
+    const DEFAULT_MAX_VOICES: usize = 8;
+
+    pub fn file_name(&self) -> String {
+        let name = match self {
+            SoundId::GunShot(gun) => return format!("{gun}_shooting.wav"),
+            SoundId::GrenadeExplosion => "grenade_explosion",
+            SoundId::Ricochet => "ricochet",
+            SoundId::ImpactConcrete => "impact_concrete",
+            SoundId::ImpactWood => "impact_wood",
+            SoundId::ImpactGlass => "impact_glass",
+            SoundId::ImpactMetal => "impact_metal",
+            SoundId::DoorOpen => "door_open",
+            SoundId::DoorClose => "door_close",
+            SoundId::DoorLocked => "door_locked",
+            SoundId::Rain => "rain",
+        };
+        format!("{name}.wav")
+    }
+
+    // Sounds that can be triggered many times in one frame get fewer voices
+    pub fn max_voices(&self) -> usize {
+        match self {
+            SoundId::GrenadeExplosion => 3,
+            SoundId::Ricochet
+            | SoundId::ImpactConcrete
+            | SoundId::ImpactWood
+            | SoundId::ImpactGlass
+            | SoundId::ImpactMetal => 4,
+            _ => SoundId::DEFAULT_MAX_VOICES,
+        }
+    }
+}
+
+// Every file under the assets folder, apart from scratch files
+pub fn asset_files() -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut dirs_to_explore = vec![PathBuf::from(ASSETS_DIR)];
+    while let Some(dir) = dirs_to_explore.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() && !path.ends_with(IGNORED_DIR) {
+                dirs_to_explore.push(path);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+// Name textures and sounds are looked up by
+pub fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// Assets the game expects to find, derived from the items, maps and music
+pub struct AssetManifest {
+    pub textures: Vec<TextureId>,
+    pub sounds: Vec<SoundId>,
+    pub files: Vec<String>,             // Read directly by path
+    pub loot_tables: Vec<&'static str>, // Also in files, these are parsed to check the items they list
+}
+
+impl AssetManifest {
+    pub fn expected() -> AssetManifest {
+        let mut manifest = AssetManifest {
+            textures: vec![TextureId::Tiles],
+            sounds: SoundId::FIXED.to_vec(),
+            files: vec![
+                PLAYER_IDLE_BITMAP.to_string(),
+                PLAYER_AIMING_BITMAP.to_string(),
+            ],
+            loot_tables: ContainerKind::ALL
+                .iter()
+                .map(ContainerKind::loot_table_path)
+                .collect(),
+        };
+
+        for item in Item::Kind::all() {
+            match item {
+                Item::Kind::Gun(gun) => {
+                    for aiming in [false, true] {
+                        manifest.textures.push(TextureId::Gun {
+                            name: gun.name,
+                            aiming,
+                        });
+                    }
+                    manifest.sounds.push(SoundId::GunShot(gun.name));
+                }
+                Item::Kind::Clothes(clothes) => {
+                    for (stance, aiming) in PlayerStance::ALL
+                        .into_iter()
+                        .flat_map(|stance| [(stance, false), (stance, true)])
+                    {
+                        manifest.textures.push(TextureId::Clothes {
+                            color: clothes.color,
+                            stance,
+                            aiming,
+                        });
+                    }
+                }
+                Item::Kind::Grenade => manifest
+                    .textures
+                    .extend([TextureId::GrenadePinned, TextureId::GrenadeUnpinned]),
+                item => manifest.textures.push(item.texture()),
+            }
+        }
+
+        for path in &manifest.loot_tables {
+            manifest.files.push(path.to_string());
+        }
+        let ambience = maps::example_world_ambience()
+            .into_iter()
+            .map(|(_, track)| track);
+        let music = MusicState::ALL.iter().map(MusicState::track);
+        for track in music.chain(ambience) {
+            let path = format!("{MUSIC_DIR}/{track}");
+            if !manifest.files.contains(&path) {
+                manifest.files.push(path);
+            }
+        }
+        manifest
+    }
+
+    // Compares the manifest against the files on disk
+    pub fn validate(&self, files: &[PathBuf]) -> AssetReport {
+        let names: HashSet<String> = files.iter().map(|path| file_name(path)).collect();
+        let mut used = HashSet::new();
+        let mut missing = vec![];
+
+        let names_needed = self
+            .textures
+            .iter()
+            .filter(|texture| !texture.is_generated())
+            .map(TextureId::file_name)
+            .chain(self.sounds.iter().map(SoundId::file_name));
+        for name in names_needed {
+            if names.contains(&name) {
+                used.insert(name);
+            } else {
+                missing.push(name);
+            }
+        }
+        for path in &self.files {
+            if Path::new(path).is_file() {
+                used.insert(file_name(Path::new(path)));
+            } else {
+                missing.push(path.clone());
+            }
+        }
+        missing.sort();
+        missing.dedup();
+
+        // Loot tables that exist but wouldn't load
+        let invalid = self
+            .loot_tables
+            .iter()
+            .filter_map(|path| {
+                let source = std::fs::read_to_string(path).ok()?;
+                LootTable::parse(&source)
+                    .err()
+                    .map(|err| format!("{path}: {err}"))
+            })
+            .collect();
+
+        let unused = files
+            .iter()
+            .filter(|path| !used.contains(&file_name(path)))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        AssetReport {
+            missing,
+            invalid,
+            unused,
+        }
+    }
+}
+
+pub struct AssetReport {
+    pub missing: Vec<String>,
+    pub invalid: Vec<String>, // Files that failed to parse, with the reason
+    pub unused: Vec<String>,
+}
+
+impl AssetReport {
+    // Missing and invalid assets are errors, unused files are only worth a warning
+    pub fn has_errors(&self) -> bool {
+        !self.missing.is_empty() || !self.invalid.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Asset check: {} missing, {} invalid, {} unused",
+            self.missing.len(),
+            self.invalid.len(),
+            self.unused.len()
+        );
+        for name in &self.missing {
+            println!("  error: missing '{name}'");
+        }
+        for reason in &self.invalid {
+            println!("  error: invalid {reason}");
+        }
+        for path in &self.unused {
+            println!("  warning: unused '{path}'");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_assets_are_all_present() {
+        let report = AssetManifest::expected().validate(&asset_files());
+        assert!(report.missing.is_empty(), "missing {:?}", report.missing);
+        assert!(report.invalid.is_empty(), "invalid {:?}", report.invalid);
+    }
+}
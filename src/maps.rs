@@ -1,6 +1,13 @@
-use std::collections::HashSet;
+use macroquad::prelude::{Color, Rect};
+use std::collections::{HashMap, HashSet};
 
-use crate::tile_map::TileMap;
+use crate::{
+    environment::{Environment, Weather},
+    items::Item,
+    loot::ContainerKind,
+    materials::TileMaterial,
+    tile_map::TileMap,
+};
 
 pub fn example_world() -> TileMap {
     TileMap {
@@ -367,10 +374,59 @@ pub fn example_world() -> TileMap {
             1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
         ],
         collidables: HashSet::new(),
+        blocked: HashMap::new(),
+        revision: 0,
+        tile_damage: HashMap::new(),
+        ambient_light: Color::new(1.0, 0.98, 0.94, 1.0),
     }
     .generate_collidables()
 }
 
+// Lootable containers placed in the example world (grid_x, grid_y, kind)
+pub fn example_world_containers() -> Vec<(u16, u16, ContainerKind)> {
+    vec![
+        (57, 59, ContainerKind::Crate),
+        (66, 55, ContainerKind::Locker),
+        (49, 47, ContainerKind::Crate),
+        (44, 60, ContainerKind::Crate),
+    ]
+}
+
+// Doors placed in the example world (grid_x, grid_y, key needed to unlock it)
+pub fn example_world_doors() -> Vec<(u16, u16, Option<Item::Key>)> {
+    vec![
+        (47, 47, None),
+        (47, 48, None),
+        (49, 52, None),
+        (50, 52, None),
+        (55, 58, Some(Item::Key::storage_key())),
+        (55, 59, Some(Item::Key::storage_key())),
+    ]
+}
+
+// The example world starts in the evening, with rain
+pub fn example_world_environment() -> Environment {
+    Environment::new(19.0, 60.0 * 10.0, Weather::Rain)
+}
+
+// Ambient sounds of the example world (area in tiles, track), the first area containing the player plays
+pub fn example_world_ambience() -> Vec<(Rect, &'static str)> {
+    vec![
+        (Rect::new(46.0, 44.0, 9.0, 10.0), "indoors.flac"),
+        (Rect::new(54.0, 45.0, 15.0, 20.0), "indoors.flac"),
+        (Rect::new(0.0, 0.0, 100.0, 100.0), "outdoors.flac"),
+    ]
+}
+
+// Lamps placed in the example world (grid_x, grid_y, color of the light)
+pub fn example_world_lamps() -> Vec<(u16, u16, Color)> {
+    vec![
+        (49, 46, Color::new(1.0, 0.9, 0.7, 1.0)),
+        (65, 50, Color::new(1.0, 0.9, 0.7, 1.0)),
+        (58, 58, Color::new(0.7, 0.85, 1.0, 1.0)),
+    ]
+}
+
 impl TileMap {
     pub fn generate_collidables(mut self) -> TileMap {
         for i in 0..self.data.len() {
@@ -393,3 +449,42 @@ pub const TILE_COLLIDER_LOOKUP: [bool; 8 * 10] = [
     false, false, true, false, false, false, false, false, false, false, false, true, true, true,
     true, true, false, false, false, false, false, false, false,
 ];
+
+// Tiles that only block bullets against crouching or prone players
+pub const TILE_LOW_COVER_LOOKUP: [bool; 8 * 10] = {
+    let mut lookup = [false; 8 * 10];
+    lookup[43 - 1] = true;
+    lookup[44 - 1] = true;
+    lookup
+};
+
+// Material of each tile, walls are concrete unless listed otherwise
+pub const TILE_MATERIAL_LOOKUP: [TileMaterial; 8 * 10] = {
+    let mut lookup = [TileMaterial::Ground; 8 * 10];
+    let mut i = 0;
+    while i < lookup.len() {
+        if TILE_COLLIDER_LOOKUP[i] {
+            lookup[i] = TileMaterial::Concrete;
+        }
+        i += 1;
+    }
+    lookup[2 - 1] = TileMaterial::Brick; // Cracked wall
+    lookup[35 - 1] = TileMaterial::Glass; // Windows
+    lookup[47 - 1] = TileMaterial::Glass;
+    lookup[60 - 1] = TileMaterial::Glass;
+    lookup[43 - 1] = TileMaterial::Wood; // Low cover furniture
+    lookup[44 - 1] = TileMaterial::Wood;
+    lookup
+};
+
+// Tile id a destructible tile is swapped for once destroyed
+pub const TILE_BROKEN_LOOKUP: [u32; 8 * 10] = {
+    let mut lookup = [0; 8 * 10];
+    lookup[2 - 1] = 10; // Rubble
+    lookup[35 - 1] = 66; // Empty window frames
+    lookup[47 - 1] = 66;
+    lookup[60 - 1] = 66;
+    lookup[43 - 1] = 12; // Bare floor
+    lookup[44 - 1] = 12;
+    lookup
+};
@@ -0,0 +1,75 @@
+use crate::manifest::SoundId;
+use macroquad::prelude::*;
+
+// What a tile is made of, decides how it reacts to bullets and explosions
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TileMaterial {
+    Ground,
+    Concrete,
+    Brick,
+    Wood,
+    Glass,
+    Metal,
+}
+
+impl TileMaterial {
+    // Damage the tile takes before breaking, None if it can't be destroyed
+    pub fn hit_points(&self) -> Option<f32> {
+        match self {
+            TileMaterial::Ground | TileMaterial::Concrete | TileMaterial::Metal => None,
+            TileMaterial::Brick => Some(150.0),
+            TileMaterial::Wood => Some(60.0),
+            TileMaterial::Glass => Some(8.0),
+        }
+    }
+
+    // Penetration power a bullet uses up passing through the tile, None if nothing gets through
+    pub fn penetration_cost(&self) -> Option<f32> {
+        match self {
+            TileMaterial::Glass => Some(0.05),
+            TileMaterial::Wood => Some(0.6),
+            _ => None,
+        }
+    }
+
+    // Fraction of its speed a bullet loses passing through the tile
+    pub fn speed_loss(&self) -> f32 {
+        match self {
+            TileMaterial::Glass => 0.1,
+            TileMaterial::Wood => 0.35,
+            _ => 1.0,
+        }
+    }
+
+    // Chance of a bullet glancing off the tile at a shallow angle
+    pub fn ricochet_chance(&self) -> f32 {
+        match self {
+            TileMaterial::Concrete => 0.6,
+            TileMaterial::Metal => 0.8,
+            TileMaterial::Brick => 0.25,
+            _ => 0.0,
+        }
+    }
+
+    pub fn impact_sound(&self) -> SoundId {
+        match self {
+            TileMaterial::Ground | TileMaterial::Concrete | TileMaterial::Brick => {
+                SoundId::ImpactConcrete
+            }
+            TileMaterial::Wood => SoundId::ImpactWood,
+            TileMaterial::Glass => SoundId::ImpactGlass,
+            TileMaterial::Metal => SoundId::ImpactMetal,
+        }
+    }
+
+    pub fn debris_color(&self) -> Color {
+        match self {
+            TileMaterial::Ground => Color::from_rgba(100, 90, 70, 255),
+            TileMaterial::Concrete => Color::from_rgba(90, 90, 100, 255),
+            TileMaterial::Brick => Color::from_rgba(150, 75, 50, 255),
+            TileMaterial::Wood => Color::from_rgba(120, 82, 45, 255),
+            TileMaterial::Glass => Color::from_rgba(190, 220, 230, 200),
+            TileMaterial::Metal => Color::from_rgba(95, 105, 115, 255),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::tile_map::TileMap;
+use macroquad::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+// Where an enemy was last seen, it fades away over time
+pub struct Ghost {
+    pub id: usize, // Index of the enemy among the other players
+    pub pos: Vec2,
+    pub time_left: f32,
+}
+
+impl Ghost {
+    pub const FADE_TIME: f32 = 8.0;
+
+    pub fn draw(&self) {
+        let alpha = (self.time_left / Ghost::FADE_TIME).clamp(0.0, 1.0) * 0.6;
+        draw_circle(
+            self.pos.x,
+            self.pos.y,
+            3.0,
+            Color::new(0.8, 0.2, 0.2, alpha * 0.3),
+        );
+        draw_circle_lines(
+            self.pos.x,
+            self.pos.y,
+            3.0,
+            0.4,
+            Color::new(0.8, 0.2, 0.2, alpha),
+        );
+    }
+}
+
+// What a player remembers of the map, used for fog of war
+// Tiles never seen stay dark, tiles seen before are shown the way they were when last seen
+#[derive(Default)]
+pub struct VisionMemory {
+    pub explored: HashMap<(u16, u16), u32>, // Tile data by grid position, as it was when last seen
+    pub ghosts: Vec<Ghost>,
+    in_sight: HashMap<usize, Vec2>, // Enemies seen this frame, by id
+}
+
+impl VisionMemory {
+    // Remembers the visible tiles and keeps track of enemies going out of sight
+    // Enemies are given as (id, position)
+    pub fn update(
+        &mut self,
+        tile_map: &TileMap,
+        visible: &HashSet<(u16, u16)>,
+        enemies: impl Iterator<Item = (usize, Vec2)>,
+    ) {
+        for &(grid_x, grid_y) in visible {
+            if let Some(tile) = tile_map.tile_data(grid_x, grid_y) {
+                self.explored.insert((grid_x, grid_y), tile);
+            }
+        }
+
+        for ghost in &mut self.ghosts {
+            ghost.time_left -= get_frame_time();
+        }
+        self.ghosts.retain(|ghost| ghost.time_left > 0.0);
+
+        let mut in_sight = HashMap::new();
+        for (id, pos) in enemies {
+            if visible.contains(&((pos.x / 8.0) as u16, (pos.y / 8.0) as u16)) {
+                self.ghosts.retain(|ghost| ghost.id != id);
+                in_sight.insert(id, pos);
+            } else if let Some(&last_pos) = self.in_sight.get(&id) {
+                self.ghosts.push(Ghost {
+                    id,
+                    pos: last_pos,
+                    time_left: Ghost::FADE_TIME,
+                });
+            }
+        }
+        self.in_sight = in_sight;
+    }
+
+    pub fn remembered_tile(&self, grid_x: u16, grid_y: u16) -> Option<u32> {
+        self.explored.get(&(grid_x, grid_y)).copied()
+    }
+
+    pub fn draw_ghosts(&self) {
+        for ghost in &self.ghosts {
+            ghost.draw();
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{assets::Assets, audio::Bus};
+use macroquad::prelude::*;
+use rodio::Sink;
+
+pub const MUSIC_DIR: &str = "assets/music";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MusicState {
+    Menu, // Inventory or a container is open
+    Exploring,
+    Combat,
+}
+
+impl MusicState {
+    pub const ALL: [MusicState; 3] = [MusicState::Menu, MusicState::Exploring, MusicState::Combat];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MusicState::Menu => "menu",
+            MusicState::Exploring => "exploring",
+            MusicState::Combat => "combat",
+        }
+    }
+
+    pub fn track(&self) -> &'static str {
+        match self {
+            MusicState::Menu => "menu.flac",
+            MusicState::Exploring => "exploring.flac",
+            MusicState::Combat => "combat.flac",
+        }
+    }
+}
+
+// Looping track streamed from disk, fading towards its target volume
+struct Track {
+    name: &'static str,
+    sink: Option<Sink>, // None if the track couldn't be played, so it isn't retried every frame
+    volume: f32,
+    target: f32,
+}
+
+// Fades the newest track in while every other track fades out
+struct Crossfader {
+    tracks: Vec<Track>, // The track being faded in is last
+    bus: Bus,
+    volume: f32,
+    fade_time: f32,
+}
+
+impl Crossfader {
+    fn new(bus: Bus, volume: f32, fade_time: f32) -> Self {
+        Self {
+            tracks: vec![],
+            bus,
+            volume,
+            fade_time,
+        }
+    }
+
+    // Switches to the track, or fades everything out if there is none
+    fn play(&mut self, assets: &Assets, name: Option<&'static str>) {
+        let current = self.tracks.last().filter(|track| track.target > 0.0);
+        if current.map(|track| track.name) == name {
+            return;
+        }
+        for track in &mut self.tracks {
+            track.target = 0.0;
+        }
+        let Some(name) = name else { return };
+
+        // A track that is still fading out fades back in from where it is
+        let track = match self.tracks.iter().position(|track| track.name == name) {
+            Some(index) => self.tracks.remove(index),
+            None => Track {
+                name,
+                sink: assets
+                    .sounds
+                    .stream_looping(&format!("{MUSIC_DIR}/{name}"), self.bus, 0.0),
+                volume: 0.0,
+                target: 0.0,
+            },
+        };
+        self.tracks.push(Track {
+            target: 1.0,
+            ..track
+        });
+    }
+
+    fn update(&mut self) {
+        let step = get_frame_time() / self.fade_time;
+        for track in &mut self.tracks {
+            track.volume = match track.volume < track.target {
+                true => (track.volume + step).min(track.target),
+                false => (track.volume - step).max(track.target),
+            };
+            if let Some(sink) = &track.sink {
+                sink.set_volume(track.volume * self.volume);
+            }
+        }
+        // Dropping the sink stops the track
+        self.tracks
+            .retain(|track| track.target > 0.0 || track.volume > 0.0);
+    }
+}
+
+// Music following what the player is doing, and ambient sounds of the area they're in
+pub struct MusicPlayer {
+    pub state: MusicState,
+    combat_intensity: f32,
+    music: Crossfader,
+    ambience: Crossfader,
+    areas: Vec<(Rect, &'static str)>, // Area in tiles and its ambient track, the first match plays
+}
+
+impl MusicPlayer {
+    const MUSIC_VOLUME: f32 = 0.6;
+    const AMBIENCE_VOLUME: f32 = 0.5;
+    const MUSIC_FADE_TIME: f32 = 2.5;
+    const AMBIENCE_FADE_TIME: f32 = 1.5;
+
+    // Combat starts once enough gunfire is heard, and only ends once it's been calm for a while
+    const COMBAT_START: f32 = 1.5;
+    const COMBAT_END: f32 = 0.2;
+    const MAX_COMBAT_INTENSITY: f32 = 4.0;
+    const COMBAT_DECAY: f32 = 0.15; // Per second
+
+    pub fn new(areas: Vec<(Rect, &'static str)>) -> Self {
+        Self {
+            state: MusicState::Exploring,
+            combat_intensity: 0.0,
+            music: Crossfader::new(
+                Bus::Music,
+                MusicPlayer::MUSIC_VOLUME,
+                MusicPlayer::MUSIC_FADE_TIME,
+            ),
+            ambience: Crossfader::new(
+                Bus::Sfx,
+                MusicPlayer::AMBIENCE_VOLUME,
+                MusicPlayer::AMBIENCE_FADE_TIME,
+            ),
+            areas,
+        }
+    }
+
+    // Gunfire and explosions the player hears build up the combat intensity
+    pub fn add_combat(&mut self, amount: f32) {
+        self.combat_intensity =
+            (self.combat_intensity + amount).min(MusicPlayer::MAX_COMBAT_INTENSITY);
+    }
+
+    pub fn update(&mut self, assets: &Assets, in_menu: bool, listener_pos: Vec2) {
+        self.combat_intensity =
+            (self.combat_intensity - MusicPlayer::COMBAT_DECAY * get_frame_time()).max(0.0);
+        let in_combat = match self.state {
+            MusicState::Combat => self.combat_intensity > MusicPlayer::COMBAT_END,
+            _ => self.combat_intensity >= MusicPlayer::COMBAT_START,
+        };
+        // Combat music keeps playing through menus
+        self.state = match (in_combat, in_menu) {
+            (true, _) => MusicState::Combat,
+            (false, true) => MusicState::Menu,
+            (false, false) => MusicState::Exploring,
+        };
+        self.music.play(assets, Some(self.state.track()));
+        self.music.update();
+
+        let grid_pos = listener_pos / 8.0;
+        let area = self.areas.iter().find(|(rect, _)| rect.contains(grid_pos));
+        self.ambience.play(assets, area.map(|(_, track)| *track));
+        self.ambience.update();
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    explosion::Explosion,
+    player::{Player, PlayerMovementState},
+    tile_map::TileMap,
+};
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NoiseKind {
+    Gunshot,
+    Footstep,
+    WeaponSwitch,
+    Explosion,
+}
+
+impl NoiseKind {
+    // Furthest the noise carries with nothing in the way
+    fn radius(&self) -> f32 {
+        match self {
+            NoiseKind::Gunshot => 8.0 * 45.0,
+            NoiseKind::Footstep => 8.0 * 7.0,
+            NoiseKind::WeaponSwitch => 8.0 * 4.0,
+            NoiseKind::Explosion => Explosion::HEARING_DISTANCE,
+        }
+    }
+
+    // How loud the noise is right where it's made
+    fn intensity(&self) -> f32 {
+        match self {
+            NoiseKind::Gunshot | NoiseKind::Explosion => 1.0,
+            NoiseKind::Footstep => 0.4,
+            NoiseKind::WeaponSwitch => 0.3,
+        }
+    }
+}
+
+// Who made a noise, so nobody reacts to their own
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NoiseSource {
+    LocalPlayer,
+    OtherPlayer(usize), // Index among the other players
+    World,
+}
+
+// Sound made somewhere in the world that players can hear
+#[derive(Clone)]
+pub struct Noise {
+    pub kind: NoiseKind,
+    pub pos: Vec2,
+    pub radius: f32,
+    pub intensity: f32,
+    pub source: NoiseSource,
+}
+
+impl Noise {
+    // Every wall between the noise and the listener takes away this much of it
+    const WALL_DAMPING: f32 = 0.6;
+
+    // Noise made by a player, footsteps get louder with speed and quieter with lower stances
+    pub fn made_by(kind: NoiseKind, player: &Player, source: NoiseSource) -> Noise {
+        let movement = match (kind, &player.movement_state) {
+            (NoiseKind::Footstep, PlayerMovementState::Sprinting) => 1.8,
+            (NoiseKind::Footstep, _) => player.stance.noise_multiplier(),
+            _ => 1.0,
+        };
+        Noise {
+            kind,
+            pos: player.pos,
+            radius: kind.radius() * movement,
+            intensity: kind.intensity(),
+            source,
+        }
+    }
+
+    pub fn explosion(explosion: &Explosion) -> Noise {
+        Noise {
+            kind: NoiseKind::Explosion,
+            pos: explosion.pos,
+            radius: NoiseKind::Explosion.radius(),
+            intensity: NoiseKind::Explosion.intensity(),
+            source: NoiseSource::World,
+        }
+    }
+
+    // How loud the noise is at the position, from 0.0 to its intensity
+    pub fn loudness_at(&self, pos: Vec2, tile_map: &TileMap) -> f32 {
+        let falloff = 1.0 - self.pos.distance(pos) / self.radius;
+        if falloff <= 0.0 {
+            return 0.0;
+        }
+        let walls = tile_map.walls_on_line(self.pos, pos);
+        self.intensity * falloff * (1.0 - Noise::WALL_DAMPING).powi(walls as i32)
+    }
+}
+
+// Noise a player heard, remembered for a moment so they can react to it
+pub struct HeardNoise {
+    pub kind: NoiseKind,
+    pub pos: Vec2,
+    pub loudness: f32,
+    pub in_sight: bool, // Whether the player could see where the noise came from
+    pub time_left: f32,
+}
+
+impl HeardNoise {
+    pub const MEMORY_TIME: f32 = 2.5;
+    // Noises quieter than this go unnoticed
+    pub const HEARING_THRESHOLD: f32 = 0.03;
+}
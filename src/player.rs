@@ -1,13 +1,24 @@
 use crate::{
     camera::GameCamera,
+    inventory::{GunSlot, Inventory, ItemStack},
     items::Item,
+    lighting::Light,
+    manifest::TextureId,
+    memory::VisionMemory,
+    noise::{HeardNoise, Noise, NoiseKind, NoiseSource},
+    status_effects::{StatusEffectKind, StatusEffects},
     tile_map::TileMap,
     utils::draw_rect,
     world::{ANGLE_PERIPHERAL_FACTOR, LINE_LENGTH, RAY_AMOUNT},
     Assets,
 };
 use macroquad::prelude::*;
-use std::f32::consts::FRAC_PI_2;
+use std::{collections::HashSet, f32::consts::FRAC_PI_2};
+
+pub const INTERACT_KEY: KeyCode = KeyCode::E;
+pub const CROUCH_KEY: KeyCode = KeyCode::C;
+pub const PRONE_KEY: KeyCode = KeyCode::Z;
+pub const FLASHLIGHT_KEY: KeyCode = KeyCode::F;
 
 pub enum PlayerController {
     User,
@@ -22,15 +33,33 @@ pub struct Player {
     pub stamina: f32,
     pub angle: f32,
     pub movement_state: PlayerMovementState,
+    pub stance: PlayerStance,
     pub stamina_state: PlayerStaminaState,
     pub inventory: Inventory,
     pub controller: PlayerController,
+    pub using: Option<ConsumableUse>,
+    pub status_effects: StatusEffects,
+    pub memory: VisionMemory,
+    pub flashlight_on: bool,
+    pub weather_vision: f32, // Vision multiplier from the weather, set by the world each frame
+    pub noises_made: Vec<NoiseKind>, // Noises made this frame, picked up by the world
+    pub heard: Vec<HeardNoise>,
+    // Keys and the trigger are ignored, set while the debug console is open
+    pub input_blocked: bool,
+    step_time: f32, // Time until the next footstep
+}
+
+// Consumable the player is in the middle of using
+pub struct ConsumableUse {
+    pub item: Item::Consumable,
+    pub time_left: f32,
 }
 
-pub struct Inventory {
-    pub clothes: Item::Clothes,
-    pub backpack: Item::Backpack,
-    pub gun: Item::Gun,
+impl ConsumableUse {
+    // Progress from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        1.0 - self.time_left / self.item.use_time
+    }
 }
 
 #[derive(PartialEq, Eq)]
@@ -40,6 +69,107 @@ pub enum PlayerMovementState {
     Sprinting,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlayerStance {
+    Standing,
+    Crouching,
+    Prone,
+}
+
+impl PlayerStance {
+    pub const ALL: [PlayerStance; 3] = [
+        PlayerStance::Standing,
+        PlayerStance::Crouching,
+        PlayerStance::Prone,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayerStance::Standing => "Standing",
+            PlayerStance::Crouching => "Crouching",
+            PlayerStance::Prone => "Prone",
+        }
+    }
+
+    pub fn hitbox_size(&self) -> f32 {
+        match self {
+            PlayerStance::Standing => 4.7,
+            PlayerStance::Crouching => 4.0,
+            PlayerStance::Prone => 3.4,
+        }
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        match self {
+            PlayerStance::Standing => 1.0,
+            PlayerStance::Crouching => 0.6,
+            PlayerStance::Prone => 0.3,
+        }
+    }
+
+    pub fn spread_multiplier(&self) -> f32 {
+        match self {
+            PlayerStance::Standing => 1.0,
+            PlayerStance::Crouching => 0.75,
+            PlayerStance::Prone => 0.5,
+        }
+    }
+
+    // How loud the player's movement is compared to standing
+    pub fn noise_multiplier(&self) -> f32 {
+        match self {
+            PlayerStance::Standing => 1.0,
+            PlayerStance::Crouching => 0.5,
+            PlayerStance::Prone => 0.25,
+        }
+    }
+
+    // Lower stances see less of their surroundings
+    pub fn fov_multiplier(&self) -> f32 {
+        match self {
+            PlayerStance::Standing => 1.0,
+            PlayerStance::Crouching => 0.85,
+            PlayerStance::Prone => 0.6,
+        }
+    }
+
+    // Prone players steady themselves and see a bit further ahead
+    pub fn vision_multiplier(&self) -> f32 {
+        match self {
+            PlayerStance::Standing => 1.0,
+            PlayerStance::Crouching => 0.95,
+            PlayerStance::Prone => 1.1,
+        }
+    }
+
+    // Textures are generated for every stance when loading clothes
+    pub fn texture_suffix(&self) -> &'static str {
+        match self {
+            PlayerStance::Standing => "",
+            PlayerStance::Crouching => "_crouching",
+            PlayerStance::Prone => "_prone",
+        }
+    }
+
+    // Brightness of the clothes textures
+    pub fn shade(&self) -> f32 {
+        match self {
+            PlayerStance::Standing => 1.0,
+            PlayerStance::Crouching => 0.85,
+            PlayerStance::Prone => 0.7,
+        }
+    }
+
+    // Size the player is drawn at compared to standing
+    pub fn draw_scale(&self) -> f32 {
+        match self {
+            PlayerStance::Standing => 1.0,
+            PlayerStance::Crouching => 0.92,
+            PlayerStance::Prone => 0.85,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum PlayerStaminaState {
     Normal,
@@ -52,12 +182,23 @@ impl Player {
     const WALKING_VELOCITY: f32 = 0.26;
     const PLAYER_ACC: f32 = 0.1; // Acceleration
     const PLAYER_DEACC: f32 = 0.05; // Deacceleration
+    const BLOCKED_TOLERANCE: f32 = 0.01; // Movement lost along an axis before it counts as blocked
+
+    const MAX_HEALTH: f32 = 100.0;
+    const BLEED_THRESHOLD: f32 = 15.0; // Hits doing at least this much damage cause bleeding
 
     const MAX_STAMINA: f32 = 100.0;
     const MIN_STAMINA_FOR_SPRINTING: f32 = 10.0;
     const STAMINA_REGEN: f32 = 0.07;
     const STAMINA_COST: f32 = 0.18;
     const STAMINA_AIMING_COST: f32 = 0.1;
+    const EXHAUSTION_TIME: f32 = 6.0;
+
+    const WALKING_STEP_TIME: f32 = 0.45;
+    const SPRINTING_STEP_TIME: f32 = 0.3;
+
+    // Players notice what's right around them, even behind their back
+    pub const NEAR_VISION: f32 = 18.0;
 
     pub fn new(grid_x: u16, grid_y: u16) -> Player {
         Player {
@@ -65,31 +206,72 @@ impl Player {
             vel: Vec2::ZERO,
             stamina: Player::MAX_STAMINA,
             movement_state: PlayerMovementState::Idle,
+            stance: PlayerStance::Standing,
             stamina_state: PlayerStaminaState::Normal,
             angle: 0.0,
-            health: 100.0,
-            inventory: Inventory {
-                clothes: Item::Clothes::red_clothes(),
-                backpack: Item::Backpack::brown_backpack(),
-                gun: Item::Gun::sawed_shotgun(),
-            },
+            health: Player::MAX_HEALTH,
+            inventory: Player::starting_inventory(),
             controller: PlayerController::None,
+            using: None,
+            status_effects: StatusEffects::default(),
+            memory: VisionMemory::default(),
+            flashlight_on: false,
+            weather_vision: 1.0,
+            noises_made: vec![],
+            heard: vec![],
+            input_blocked: false,
+            step_time: 0.0,
         }
     }
 
+    fn starting_inventory() -> Inventory {
+        let mut inventory = Inventory::new(
+            Item::Clothes::red_clothes(),
+            Some(Item::Backpack::brown_backpack()),
+        );
+        inventory.primary = Some(Item::Gun::sawed_shotgun());
+        inventory.secondary = Some(Item::Gun::sniper());
+        inventory.throwable = Some(ItemStack::with_count(Item::Kind::Grenade, 3));
+        inventory.add_item(ItemStack::new(Item::Kind::Clothes(
+            Item::Clothes::blue_clothes(),
+        )));
+        inventory.add_item(ItemStack::new(Item::Kind::Clothes(
+            Item::Clothes::dark_clothes(),
+        )));
+        inventory.add_item(ItemStack::new(Item::Kind::Backpack(
+            Item::Backpack::black_backpack(),
+        )));
+        inventory.add_item(ItemStack::with_count(
+            Item::Kind::Consumable(Item::Consumable::bandage()),
+            2,
+        ));
+        inventory.add_item(ItemStack::new(Item::Kind::Consumable(
+            Item::Consumable::medkit(),
+        )));
+        inventory.add_item(ItemStack::new(Item::Kind::Consumable(
+            Item::Consumable::stimulant(),
+        )));
+        inventory
+    }
+
     pub fn update(&mut self, camera: &GameCamera, tile_map: &TileMap) {
         match self.controller {
             PlayerController::User => {
                 // Update
+                self.handle_stance(tile_map);
                 self.handle_movement_state();
                 self.handle_velocity();
-                self.handle_collisions(tile_map);
                 self.handle_stamina();
+                self.handle_health();
                 self.handle_gun_controls();
-                self.handle_clothes_controls();
+                self.handle_consumables();
+                self.handle_footsteps();
+                if self.key_pressed(FLASHLIGHT_KEY) {
+                    self.flashlight_on = !self.flashlight_on;
+                }
 
                 // Apply
-                self.apply_velocity();
+                self.apply_velocity(tile_map);
                 self.update_angle_to_mouse(camera);
             }
             PlayerController::None => {}
@@ -97,34 +279,179 @@ impl Player {
     }
 
     fn handle_gun_controls(&mut self) {
-        if is_key_pressed(KeyCode::Key1) {
-            self.inventory.gun = Item::Gun::sawed_shotgun()
+        let selected_gun = self.inventory.selected_gun;
+        if self.key_pressed(KeyCode::Key1) {
+            self.inventory.selected_gun = GunSlot::Primary
+        }
+        if self.key_pressed(KeyCode::Key2) {
+            self.inventory.selected_gun = GunSlot::Secondary
+        }
+        if self.inventory.selected_gun != selected_gun {
+            self.noises_made.push(NoiseKind::WeaponSwitch);
+        }
+    }
+
+    // Moving players make a footstep noise every so often, sprinting ones more often
+    fn handle_footsteps(&mut self) {
+        let step_time = match self.movement_state {
+            PlayerMovementState::Idle => {
+                self.step_time = 0.0;
+                return;
+            }
+            PlayerMovementState::Walking => Player::WALKING_STEP_TIME,
+            PlayerMovementState::Sprinting => Player::SPRINTING_STEP_TIME,
+        };
+        self.step_time -= get_frame_time();
+        if self.step_time <= 0.0 {
+            self.step_time = step_time / self.stance.speed_multiplier();
+            self.noises_made.push(NoiseKind::Footstep);
+        }
+    }
+
+    // Starts using a consumable if the player has one and isn't already using something
+    pub fn start_using(&mut self, item: Item::Consumable) {
+        if self.using.is_some() || self.inventory.count(&Item::Kind::Consumable(item.clone())) == 0
+        {
+            return;
+        }
+        self.using = Some(ConsumableUse {
+            time_left: item.use_time,
+            item,
+        });
+    }
+
+    fn handle_consumables(&mut self) {
+        if !self.inventory.is_open {
+            if self.key_pressed(KeyCode::Key4) {
+                self.start_using(Item::Consumable::bandage());
+            }
+            if self.key_pressed(KeyCode::Key5) {
+                self.start_using(Item::Consumable::medkit());
+            }
+            if self.key_pressed(KeyCode::Key6) {
+                self.start_using(Item::Consumable::stimulant());
+            }
+        }
+
+        // Sprinting or shooting interrupts using an item, and the item is not used up
+        if self.movement_state == PlayerMovementState::Sprinting || self.is_shooting() {
+            self.using = None;
         }
-        if is_key_pressed(KeyCode::Key2) {
-            self.inventory.gun = Item::Gun::sniper()
+
+        let Some(using) = &mut self.using else { return };
+        using.time_left -= get_frame_time();
+        if using.time_left <= 0.0 {
+            let item = using.item.clone();
+            self.using = None;
+            if self
+                .inventory
+                .remove_one(&Item::Kind::Consumable(item.clone()))
+            {
+                self.apply_consumable(&item);
+            }
         }
     }
 
-    fn handle_clothes_controls(&mut self) {
-        if is_key_pressed(KeyCode::Key3) {
-            self.inventory.clothes = Item::Clothes::blue_clothes()
+    fn apply_consumable(&mut self, item: &Item::Consumable) {
+        self.health = (self.health + item.heal).min(Player::MAX_HEALTH);
+        if item.stops_bleeding {
+            self.status_effects.remove(StatusEffectKind::Bleeding);
+        }
+        if let Some((effect, duration)) = item.effect {
+            self.status_effects.add(effect, duration);
         }
-        if is_key_pressed(KeyCode::Key4) {
-            self.inventory.clothes = Item::Clothes::dark_clothes()
+    }
+
+    // Handles status effects and the health they drain or restore
+    fn handle_health(&mut self) {
+        let health_per_second = self.status_effects.modifiers().health_per_second;
+        self.health =
+            (self.health + health_per_second * get_frame_time()).clamp(0.0, Player::MAX_HEALTH);
+        self.status_effects.update(get_frame_time());
+    }
+
+    // Multiplier for the spread of the player's gun
+    pub fn spread_multiplier(&self) -> f32 {
+        self.status_effects.modifiers().spread * self.stance.spread_multiplier()
+    }
+
+    // How far the player can see
+    pub fn vision_length(&self) -> f32 {
+        LINE_LENGTH
+            * self.status_effects.modifiers().vision
+            * self.stance.vision_multiplier()
+            * self.weather_vision
+    }
+
+    // Direction the player is looking in
+    pub fn facing(&self) -> Vec2 {
+        Vec2::from_angle(self.angle + FRAC_PI_2)
+    }
+
+    // Angle of the player's vision cone
+    pub fn vision_fov(&self) -> f32 {
+        std::f32::consts::PI * self.stance.fov_multiplier()
+    }
+
+    // Light shining where the player is facing, if their flashlight is on
+    pub fn flashlight(&self) -> Option<Light> {
+        self.flashlight_on.then(|| Light {
+            pos: self.pos,
+            radius: 8.0 * 16.0,
+            color: Color::new(1.0, 0.97, 0.85, 1.0),
+            intensity: 1.4,
+            cone: Some((self.facing(), 0.8)),
+        })
+    }
+
+    // Tiles in the vision cone, and the ones right around the player
+    pub fn visible_tiles(&self, tile_map: &TileMap) -> HashSet<(u16, u16)> {
+        let mut tiles = tile_map.field_of_view(
+            self.pos,
+            self.facing(),
+            self.vision_fov() * ANGLE_PERIPHERAL_FACTOR,
+            self.vision_length() * ANGLE_PERIPHERAL_FACTOR,
+        );
+        tiles.extend(tile_map.field_of_view(
+            self.pos,
+            self.facing(),
+            std::f32::consts::TAU,
+            Player::NEAR_VISION,
+        ));
+        tiles
+    }
+
+    // Toggles crouching and going prone, pressing the key of the current stance stands back up
+    fn handle_stance(&mut self, tile_map: &TileMap) {
+        let mut stance = self.stance;
+        if self.key_pressed(CROUCH_KEY) {
+            stance = match stance {
+                PlayerStance::Crouching => PlayerStance::Standing,
+                _ => PlayerStance::Crouching,
+            };
         }
-        if is_key_pressed(KeyCode::Key5) {
-            self.inventory.clothes = Item::Clothes::red_clothes()
+        if self.key_pressed(PRONE_KEY) {
+            stance = match stance {
+                PlayerStance::Prone => PlayerStance::Standing,
+                _ => PlayerStance::Prone,
+            };
         }
-        if is_key_pressed(KeyCode::Key6) {
-            self.inventory.backpack = Item::Backpack::black_backpack()
+        // Sprinting stands the player up
+        if self.key_down(KeyCode::LeftShift) && self.is_moving() {
+            stance = PlayerStance::Standing;
         }
-        if is_key_pressed(KeyCode::Key7) {
-            self.inventory.backpack = Item::Backpack::brown_backpack()
+
+        // Getting up needs room for the bigger hitbox
+        let old_stance = std::mem::replace(&mut self.stance, stance);
+        if tile_map.rect_collides_with_tile(self.get_hitbox()) {
+            self.stance = old_stance;
         }
     }
 
     // Handle inputs and calculate velocity
     fn handle_velocity(&mut self) {
+        let encumbrance = self.inventory.encumbrance();
+        let acceleration = Player::PLAYER_ACC * encumbrance.acceleration_multiplier();
         let player_max_vel: f32 = {
             if self.movement_state == PlayerMovementState::Sprinting
                 && self.stamina_state == PlayerStaminaState::Normal
@@ -133,40 +460,46 @@ impl Player {
             } else {
                 Player::WALKING_VELOCITY
             }
-        };
+        } * (1.0 - self.inventory.clothes.movement_penalty)
+            * self.status_effects.modifiers().movement_speed
+            * encumbrance.speed_multiplier()
+            * self.stance.speed_multiplier();
+
+        // Speed above the max (from knockback) is kept and bled off instead of clamped instantly
+        let speed_cap = player_max_vel.max(self.vel.length() - Player::PLAYER_DEACC);
 
         // Handle movement inputs
-        if is_key_down(KeyCode::W) {
-            self.vel.y -= Player::PLAYER_ACC;
+        if self.key_down(KeyCode::W) {
+            self.vel.y -= acceleration;
         }
-        if is_key_down(KeyCode::S) {
-            self.vel.y += Player::PLAYER_ACC;
+        if self.key_down(KeyCode::S) {
+            self.vel.y += acceleration;
         }
-        if is_key_down(KeyCode::A) {
-            self.vel.x -= Player::PLAYER_ACC;
+        if self.key_down(KeyCode::A) {
+            self.vel.x -= acceleration;
         }
-        if is_key_down(KeyCode::D) {
-            self.vel.x += Player::PLAYER_ACC;
+        if self.key_down(KeyCode::D) {
+            self.vel.x += acceleration;
         }
 
         // Normalize velocity to maintain constant speed
         let magnitude = (self.vel.x.powi(2) + self.vel.y.powi(2)).sqrt();
-        if magnitude > player_max_vel {
-            self.vel.x = (self.vel.x / magnitude) * player_max_vel;
-            self.vel.y = (self.vel.y / magnitude) * player_max_vel;
+        if magnitude > speed_cap {
+            self.vel.x = (self.vel.x / magnitude) * speed_cap;
+            self.vel.y = (self.vel.y / magnitude) * speed_cap;
         }
 
         // Deacceleration logic when keys are not pressed
-        if self.vel.x > 0.0 && !is_key_down(KeyCode::D) {
+        if self.vel.x > 0.0 && !self.key_down(KeyCode::D) {
             self.vel.x = (self.vel.x - Player::PLAYER_DEACC).max(0.0);
         }
-        if self.vel.y > 0.0 && !is_key_down(KeyCode::S) {
+        if self.vel.y > 0.0 && !self.key_down(KeyCode::S) {
             self.vel.y = (self.vel.y - Player::PLAYER_DEACC).max(0.0);
         }
-        if self.vel.x < 0.0 && !is_key_down(KeyCode::A) {
+        if self.vel.x < 0.0 && !self.key_down(KeyCode::A) {
             self.vel.x = (self.vel.x + Player::PLAYER_DEACC).min(0.0);
         }
-        if self.vel.y < 0.0 && !is_key_down(KeyCode::W) {
+        if self.vel.y < 0.0 && !self.key_down(KeyCode::W) {
             self.vel.y = (self.vel.y + Player::PLAYER_DEACC).min(0.0);
         }
     }
@@ -177,20 +510,29 @@ impl Player {
         if self.movement_state == PlayerMovementState::Sprinting
             && self.stamina_state == PlayerStaminaState::Normal
         {
-            self.stamina = (self.stamina - Player::STAMINA_COST * get_frame_time() * 60.0).max(0.0);
+            let stamina_cost = Player::STAMINA_COST
+                * (1.0 + self.inventory.clothes.stamina_penalty)
+                * self.status_effects.modifiers().stamina_cost
+                * self.inventory.encumbrance().stamina_multiplier();
+            self.stamina = (self.stamina - stamina_cost * get_frame_time() * 60.0).max(0.0);
             // Deplete stamina for running
         }
         if self.is_aiming() {
             self.stamina =
                 (self.stamina - Player::STAMINA_AIMING_COST * get_frame_time() * 60.0).max(0.0);
         }
-        // Enter recovering stamina state
+        // Enter recovering stamina state, running out of stamina leaves the player exhausted
         if self.stamina <= 0.0 {
+            if self.stamina_state == PlayerStaminaState::Normal {
+                self.status_effects
+                    .add(StatusEffectKind::Exhaustion, Player::EXHAUSTION_TIME);
+            }
             self.stamina_state = PlayerStaminaState::Recovering
         }
         // Regen stamina
-        self.stamina = (self.stamina + Player::STAMINA_REGEN * get_frame_time() * 60.0)
-            .min(Player::MAX_STAMINA);
+        let stamina_regen = Player::STAMINA_REGEN * self.status_effects.modifiers().stamina_regen;
+        self.stamina =
+            (self.stamina + stamina_regen * get_frame_time() * 60.0).min(Player::MAX_STAMINA);
 
         if self.stamina >= Player::MIN_STAMINA_FOR_SPRINTING {
             self.stamina_state = PlayerStaminaState::Normal;
@@ -199,8 +541,12 @@ impl Player {
 
     // Update player movement state based on inputs
     fn handle_movement_state(&mut self) {
+        // Overloaded players are too heavy to sprint
+        let wants_to_sprint = self.key_down(KeyCode::LeftShift)
+            && self.inventory.encumbrance().can_sprint()
+            && self.stance == PlayerStance::Standing;
         self.movement_state = {
-            match (self.is_moving(), is_key_down(KeyCode::LeftShift)) {
+            match (self.is_moving(), wants_to_sprint) {
                 (true, true) => PlayerMovementState::Sprinting,
                 (true, false) => PlayerMovementState::Walking,
                 _ => PlayerMovementState::Idle,
@@ -208,10 +554,6 @@ impl Player {
         }
     }
 
-    fn apply_velocity(&mut self) {
-        self.pos += self.vel * get_frame_time() * 60.0;
-    }
-
     // Function to update the player's angle towards the mouse position
     fn update_angle_to_mouse(&mut self, camera: &GameCamera) {
         let mouse_pos: Vec2 = mouse_position().into();
@@ -220,29 +562,89 @@ impl Player {
         self.angle = f32::atan2(-mouse_dist_center.x, mouse_dist_center.y);
     }
 
+    // Whether the position is in the player's sight, with nothing in the way
+    pub fn can_see(&self, pos: Vec2, tile_map: &TileMap) -> bool {
+        let offset = pos - self.pos;
+        let in_view = offset.length() <= Player::NEAR_VISION
+            || (offset.length() <= self.vision_length()
+                && self.facing().angle_between(offset).abs() <= self.vision_fov() / 2.0);
+        in_view && tile_map.has_line_of_sight(self.pos, pos)
+    }
+
+    // Remembers the noises loud enough to hear, ignoring the player's own
+    pub fn hear(&mut self, noises: &[Noise], listener: NoiseSource, tile_map: &TileMap) {
+        for heard in &mut self.heard {
+            heard.time_left -= get_frame_time();
+        }
+        self.heard.retain(|heard| heard.time_left > 0.0);
+
+        for noise in noises.iter().filter(|noise| noise.source != listener) {
+            let loudness = noise.loudness_at(self.pos, tile_map);
+            if loudness < HeardNoise::HEARING_THRESHOLD {
+                continue;
+            }
+            self.heard.push(HeardNoise {
+                kind: noise.kind,
+                pos: noise.pos,
+                loudness,
+                in_sight: self.can_see(noise.pos, tile_map),
+                time_left: HeardNoise::MEMORY_TIME,
+            });
+        }
+    }
+
+    // Loudest noise the player heard recently, if any
+    pub fn loudest_heard(&self) -> Option<&HeardNoise> {
+        self.heard
+            .iter()
+            .max_by(|a, b| a.loudness.total_cmp(&b.loudness))
+    }
+
     pub fn turn_to_face(&mut self, pos: Vec2, camera: &GameCamera) {
         let pos = camera.world_to_screen(pos);
         let dist = pos - camera.world_to_screen(self.pos);
         self.angle = f32::atan2(-dist.x, dist.y);
     }
 
+    // Key state that reads as released while input is blocked
+    fn key_down(&self, key: KeyCode) -> bool {
+        !self.input_blocked && is_key_down(key)
+    }
+
+    fn key_pressed(&self, key: KeyCode) -> bool {
+        !self.input_blocked && is_key_pressed(key)
+    }
+
     // Checks if any keys are down that would move the player
     fn is_moving(&self) -> bool {
-        if is_key_down(KeyCode::W) {
+        if self.key_down(KeyCode::W) {
             return true;
         }
-        if is_key_down(KeyCode::S) {
+        if self.key_down(KeyCode::S) {
             return true;
         }
-        if is_key_down(KeyCode::A) {
+        if self.key_down(KeyCode::A) {
             return true;
         }
-        if is_key_down(KeyCode::D) {
+        if self.key_down(KeyCode::D) {
             return true;
         }
         false
     }
 
+    // Checks if the player pulled the trigger this frame
+    pub fn is_shooting(&self) -> bool {
+        match self.controller {
+            PlayerController::User => {
+                (is_mouse_button_pressed(MouseButton::Left) || self.key_pressed(KeyCode::Space))
+                    && is_mouse_button_down(MouseButton::Right)
+                    && !self.inventory.is_open
+                    && !self.input_blocked
+            }
+            PlayerController::None => false,
+        }
+    }
+
     fn is_aiming(&self) -> bool {
         match self.controller {
             PlayerController::User => is_mouse_button_down(MouseButton::Right),
@@ -275,7 +677,7 @@ impl Player {
 
     // Player hitbox
     pub fn get_hitbox(&self) -> Rect {
-        let rect_size = 4.7;
+        let rect_size = self.stance.hitbox_size();
         Rect {
             x: self.pos.x - rect_size / 2.0,
             y: self.pos.y - rect_size / 2.0,
@@ -284,30 +686,46 @@ impl Player {
         }
     }
 
-    // Handles collisions between world and player hitbox
-    fn handle_collisions(&mut self, tile_map: &TileMap) {
-        let player_hitbox = self.get_hitbox();
+    // Moves the player, sliding along anything in the way
+    // Velocity into a wall is dropped so it doesn't build up while pushing against it
+    fn apply_velocity(&mut self, tile_map: &TileMap) {
+        let motion = self.vel * get_frame_time() * 60.0;
+        let moved = tile_map.sweep_rect(self.get_hitbox(), motion);
+        self.pos += moved;
 
-        // X vel
-        if tile_map.rect_collides_with_tile(Rect::new(
-            player_hitbox.x + self.vel.x * get_frame_time() * 60.0,
-            player_hitbox.y,
-            player_hitbox.w,
-            player_hitbox.h,
-        )) {
+        if (moved.x - motion.x).abs() > Player::BLOCKED_TOLERANCE && moved.x.abs() < motion.x.abs()
+        {
             self.vel.x = 0.0;
         }
-        // Y vel
-        if tile_map.rect_collides_with_tile(Rect::new(
-            player_hitbox.x,
-            player_hitbox.y + self.vel.y * get_frame_time() * 60.0,
-            player_hitbox.w,
-            player_hitbox.h,
-        )) {
+        if (moved.y - motion.y).abs() > Player::BLOCKED_TOLERANCE && moved.y.abs() < motion.y.abs()
+        {
             self.vel.y = 0.0;
         }
     }
 
+    // Radius of the circle used to keep players from overlapping each other
+    pub fn collision_radius(&self) -> f32 {
+        self.stance.hitbox_size() / 2.0
+    }
+
+    // Pushes the player by the offset without pushing them into walls
+    // Returns how far the player actually moved
+    pub fn push(&mut self, offset: Vec2, tile_map: &TileMap) -> Vec2 {
+        let moved = tile_map.sweep_rect(self.get_hitbox(), offset);
+        self.pos += moved;
+        moved
+    }
+
+    // Damage is reduced by any armor the player is wearing
+    pub fn take_damage(&mut self, damage: f32) {
+        let damage = self.inventory.clothes.absorb(damage);
+        self.health = (self.health - damage).max(0.0);
+        if damage >= Player::BLEED_THRESHOLD {
+            self.status_effects
+                .add(StatusEffectKind::Bleeding, f32::INFINITY);
+        }
+    }
+
     pub fn _tp_grid(&mut self, grid_x: u16, grid_y: u16) {
         self.pos = Vec2::new(grid_x as f32 * 8.0 + 0.5, grid_y as f32 * 8.0 + 0.5);
     }
@@ -322,9 +740,9 @@ impl Player {
     // Draw texture on player
     fn draw_on_player(&self, texture: &Texture2D) {
         const CENTER_OFFSET: f32 = 1.0 / 6.0;
-        const SCALE_FACTOR: f32 = 17.0 * 1.3333333;
+        let scale_factor = 17.0 * 1.3333333 * self.stance.draw_scale();
 
-        let half_scale = SCALE_FACTOR / 2.0;
+        let half_scale = scale_factor / 2.0;
         let x_pos = self.pos.x - half_scale + CENTER_OFFSET;
         let y_pos = self.pos.y - half_scale - CENTER_OFFSET;
 
@@ -336,7 +754,7 @@ impl Player {
             DrawTextureParams {
                 rotation: self.angle,
                 pivot: Some(self.pos),
-                dest_size: Some(Vec2::new(SCALE_FACTOR, SCALE_FACTOR)),
+                dest_size: Some(Vec2::new(scale_factor, scale_factor)),
                 ..Default::default()
             },
         );
@@ -355,28 +773,36 @@ impl Player {
 
     pub fn draw(&self, assets: &Assets) {
         // Get gun texture
-        let gun_name = self.inventory.gun.name;
-        let gun_texture = match self.is_aiming() {
-            true => assets.get_texture(&format!("{gun_name}_aiming.png")),
-            false => assets.get_texture(&format!("{gun_name}_idle.png")),
-        };
-
-        // Get player texture
-        let clothes_name = self.inventory.clothes.name;
-        let player_texture = match self.is_aiming() {
-            true => assets.get_texture(&format!("{clothes_name}_clothes_aiming.png")),
-            false => assets.get_texture(&format!("{clothes_name}_clothes_idle.png")),
-        };
+        let gun_texture = self.inventory.gun().map(|gun| {
+            assets.get_texture(TextureId::Gun {
+                name: gun.name,
+                aiming: self.is_aiming(),
+            })
+        });
 
         // Get player texture
-        let backpack_name = self.inventory.backpack.name;
-        let backpack_texture = assets.get_texture(&format!("{backpack_name}.png"));
+        let player_texture = assets.get_texture(TextureId::Clothes {
+            color: self.inventory.clothes.color,
+            stance: self.stance,
+            aiming: self.is_aiming(),
+        });
+
+        // Get backpack texture
+        let backpack_texture = self
+            .inventory
+            .backpack
+            .as_ref()
+            .map(|backpack| assets.get_texture(TextureId::Backpack(backpack.name)));
 
         // Draw entire player
         self.draw_player_shadow();
-        self.draw_on_player(&gun_texture);
+        if let Some(gun_texture) = &gun_texture {
+            self.draw_on_player(gun_texture);
+        }
         self.draw_on_player(&player_texture);
-        self.draw_on_player(&backpack_texture);
+        if let Some(backpack_texture) = &backpack_texture {
+            self.draw_on_player(backpack_texture);
+        }
     }
 
     pub fn draw_hitbox(&self) {
@@ -389,7 +815,7 @@ impl Player {
 
     pub fn _draw_debug_rays(&self) {
         for angle in self.get_player_rays(
-            std::f32::consts::PI * ANGLE_PERIPHERAL_FACTOR,
+            self.vision_fov() * ANGLE_PERIPHERAL_FACTOR,
             LINE_LENGTH * ANGLE_PERIPHERAL_FACTOR,
         ) {
             draw_line(
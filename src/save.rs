@@ -0,0 +1,112 @@
+use crate::{
+    memory::{Ghost, VisionMemory},
+    player::Player,
+};
+use macroquad::prelude::*;
+
+pub const SAVE_PATH: &str = "save.txt";
+pub const SAVE_KEY: KeyCode = KeyCode::F5;
+pub const LOAD_KEY: KeyCode = KeyCode::F9;
+
+// Game state kept between sessions, stored as plain text
+// Each line is one of
+//   "player <x> <y> <health>"
+//   "explored <grid x> <grid y> <tile data>"
+//   "ghost <id> <x> <y> <time left>"
+// Empty lines and lines starting with # are ignored
+pub struct SaveGame {
+    pub pos: Vec2,
+    pub health: f32,
+    pub memory: VisionMemory,
+}
+
+impl SaveGame {
+    fn serialize(player: &Player) -> String {
+        let mut lines = vec![
+            "# Save file".to_string(),
+            format!("player {} {} {}", player.pos.x, player.pos.y, player.health),
+        ];
+
+        // Sorted so saving twice gives the same file
+        let mut explored: Vec<_> = player.memory.explored.iter().collect();
+        explored.sort();
+        for ((grid_x, grid_y), tile) in explored {
+            lines.push(format!("explored {grid_x} {grid_y} {tile}"));
+        }
+        for ghost in &player.memory.ghosts {
+            lines.push(format!(
+                "ghost {} {} {} {}",
+                ghost.id, ghost.pos.x, ghost.pos.y, ghost.time_left
+            ));
+        }
+        lines.join("\n")
+    }
+
+    pub fn parse(source: &str) -> Result<SaveGame, String> {
+        let mut save = SaveGame {
+            pos: Vec2::ZERO,
+            health: 0.0,
+            memory: VisionMemory::default(),
+        };
+        let mut has_player = false;
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |reason: &str| format!("line {}: {reason} ({line})", line_number + 1);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let word = |index: usize| words.get(index).ok_or_else(|| error("missing value"));
+            let number = |index: usize| -> Result<f32, String> {
+                word(index)?.parse().map_err(|_| error("expected a number"))
+            };
+            let integer = |index: usize| -> Result<u32, String> {
+                word(index)?
+                    .parse()
+                    .map_err(|_| error("expected a whole number"))
+            };
+
+            match words[0] {
+                "player" => {
+                    save.pos = Vec2::new(number(1)?, number(2)?);
+                    save.health = number(3)?;
+                    has_player = true;
+                }
+                "explored" => {
+                    let grid_x = u16::try_from(integer(1)?).map_err(|_| error("out of range"))?;
+                    let grid_y = u16::try_from(integer(2)?).map_err(|_| error("out of range"))?;
+                    save.memory.explored.insert((grid_x, grid_y), integer(3)?);
+                }
+                "ghost" => save.memory.ghosts.push(Ghost {
+                    id: integer(1)? as usize,
+                    pos: Vec2::new(number(2)?, number(3)?),
+                    time_left: number(4)?,
+                }),
+                _ => return Err(error("unknown entry")),
+            }
+        }
+        match has_player {
+            true => Ok(save),
+            false => Err("missing player".to_string()),
+        }
+    }
+
+    pub fn save(path: &str, player: &Player) -> Result<(), String> {
+        std::fs::write(path, SaveGame::serialize(player))
+            .map_err(|err| format!("Failed to write save '{path}': {err}"))
+    }
+
+    // Loads the save into the player, the player is left as is if the save can't be read
+    pub fn load(path: &str, player: &mut Player) -> Result<(), String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read save '{path}': {err}"))?;
+        let save =
+            SaveGame::parse(&source).map_err(|err| format!("Invalid save '{path}': {err}"))?;
+        player.pos = save.pos;
+        player.vel = Vec2::ZERO;
+        player.health = save.health;
+        player.memory = save.memory;
+        Ok(())
+    }
+}
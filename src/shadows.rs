@@ -0,0 +1,205 @@
+use crate::{
+    camera::GameCamera, player::Player, tile_map::TileMap, world::ANGLE_PERIPHERAL_FACTOR,
+};
+use macroquad::prelude::*;
+use std::collections::HashSet;
+
+// Renders what the player can't see as shadow
+// A mask is drawn each frame from the player's visibility polygon and memory
+// Red is where the player can see, which clips entities, and green is how lit the tiles are
+// The mask is drawn at a lower resolution and stretched over the screen, which softens its edges
+pub struct ShadowRenderer {
+    mask: RenderTarget,
+    entities: RenderTarget, // Entities are drawn here first, so they can be clipped by the mask
+    shadow_material: Material,
+    clip_material: Material,
+}
+
+impl ShadowRenderer {
+    const MASK_DOWNSCALE: f32 = 4.0;
+    // Brightness of remembered tiles out of sight, tiles never seen are black
+    const REMEMBERED_LIGHT: f32 = 0.55;
+
+    pub fn new() -> Self {
+        let (mask, entities) = ShadowRenderer::render_targets();
+        let shadow_material = load_material(
+            ShaderSource::Glsl {
+                vertex: VERTEX_SHADER,
+                fragment: SHADOW_FRAGMENT_SHADER,
+            },
+            Default::default(),
+        )
+        .unwrap();
+        let clip_material = load_material(
+            ShaderSource::Glsl {
+                vertex: VERTEX_SHADER,
+                fragment: CLIP_FRAGMENT_SHADER,
+            },
+            MaterialParams {
+                textures: vec!["Mask".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        Self {
+            mask,
+            entities,
+            shadow_material,
+            clip_material,
+        }
+    }
+
+    fn render_targets() -> (RenderTarget, RenderTarget) {
+        let mask = render_target(
+            (screen_width() / ShadowRenderer::MASK_DOWNSCALE).ceil() as u32,
+            (screen_height() / ShadowRenderer::MASK_DOWNSCALE).ceil() as u32,
+        );
+        mask.texture.set_filter(FilterMode::Linear);
+        let entities = render_target(screen_width() as u32, screen_height() as u32);
+        entities.texture.set_filter(FilterMode::Nearest);
+        (mask, entities)
+    }
+
+    // Render targets follow the size of the window
+    fn resize(&mut self) {
+        if self.entities.texture.width() as u32 == screen_width() as u32
+            && self.entities.texture.height() as u32 == screen_height() as u32
+        {
+            return;
+        }
+        self.mask.delete();
+        self.entities.delete();
+        (self.mask, self.entities) = ShadowRenderer::render_targets();
+    }
+
+    // Draws the area the player can see and remembers into the mask
+    pub fn update_mask(
+        &mut self,
+        tile_map: &TileMap,
+        player: &Player,
+        camera: &GameCamera,
+        visible_tiles: &HashSet<(u16, u16)>,
+    ) {
+        self.resize();
+        let fov = player.vision_fov() * ANGLE_PERIPHERAL_FACTOR;
+        let length = player.vision_length() * ANGLE_PERIPHERAL_FACTOR;
+
+        set_camera(&camera.with_render_target(&self.mask));
+        clear_background(BLACK);
+
+        let remembered = Color::new(0.0, ShadowRenderer::REMEMBERED_LIGHT, 0.0, 1.0);
+        for (grid_x, grid_y) in camera.get_visible_tiles(tile_map) {
+            if player.memory.remembered_tile(grid_x, grid_y).is_some() {
+                draw_rectangle(
+                    grid_x as f32 * 8.0,
+                    grid_y as f32 * 8.0,
+                    8.0,
+                    8.0,
+                    remembered,
+                );
+            }
+        }
+
+        let cone = tile_map.visibility_polygon(player.pos, player.facing(), fov, length);
+        let near = tile_map.visibility_polygon(
+            player.pos,
+            player.facing(),
+            std::f32::consts::TAU,
+            Player::NEAR_VISION,
+        );
+        for polygon in [cone, near] {
+            for edge in polygon.windows(2) {
+                draw_triangle(player.pos, edge[0], edge[1], WHITE);
+            }
+        }
+
+        // Walls stop the polygon at their faces, so the walls that are seen get lit whole
+        for &(grid_x, grid_y) in visible_tiles {
+            if tile_map.is_collider(grid_x, grid_y) {
+                draw_rectangle(grid_x as f32 * 8.0, grid_y as f32 * 8.0, 8.0, 8.0, WHITE);
+            }
+        }
+    }
+
+    // Darkens everything drawn so far outside of the mask
+    pub fn draw_shadows(&self, camera: &GameCamera) {
+        set_default_camera();
+        gl_use_material(&self.shadow_material);
+        self.draw_fullscreen(&self.mask.texture);
+        gl_use_default_material();
+        set_camera(camera);
+    }
+
+    // Entities drawn until draw_clipped_entities are only shown inside of the mask
+    pub fn begin_entities(&self, camera: &GameCamera) {
+        set_camera(&camera.with_render_target(&self.entities));
+        clear_background(Color::new(0.0, 0.0, 0.0, 0.0));
+    }
+
+    pub fn draw_clipped_entities(&self, camera: &GameCamera) {
+        set_default_camera();
+        self.clip_material
+            .set_texture("Mask", self.mask.texture.clone());
+        gl_use_material(&self.clip_material);
+        self.draw_fullscreen(&self.entities.texture);
+        gl_use_default_material();
+        set_camera(camera);
+    }
+
+    fn draw_fullscreen(&self, texture: &Texture2D) {
+        draw_texture_ex(
+            texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(screen_width(), screen_height())),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+pub const VERTEX_SHADER: &str = "#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+";
+
+// Black, fading in where the mask is dark
+const SHADOW_FRAGMENT_SHADER: &str = "#version 100
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0 - texture2D(Texture, uv).g);
+}
+";
+
+// Entities, faded out where the mask is dark
+const CLIP_FRAGMENT_SHADER: &str = "#version 100
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+uniform sampler2D Mask;
+
+void main() {
+    lowp vec4 entity = texture2D(Texture, uv) * color;
+    gl_FragColor = vec4(entity.rgb, entity.a * texture2D(Mask, uv).r);
+}
+";
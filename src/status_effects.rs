@@ -0,0 +1,146 @@
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusEffectKind {
+    Bleeding,
+    Concussion,
+    Suppression,
+    Exhaustion,
+    Stimulated,
+}
+
+#[derive(Clone)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    pub stacks: u32,
+    pub time_left: f32,
+}
+
+// Combined effect of everything the player is affected by
+// Everything except health_per_second is a multiplier
+pub struct StatusModifiers {
+    pub movement_speed: f32,
+    pub spread: f32,
+    pub stamina_cost: f32,
+    pub stamina_regen: f32,
+    pub vision: f32,
+    pub health_per_second: f32,
+}
+
+impl Default for StatusModifiers {
+    fn default() -> Self {
+        Self {
+            movement_speed: 1.0,
+            spread: 1.0,
+            stamina_cost: 1.0,
+            stamina_regen: 1.0,
+            vision: 1.0,
+            health_per_second: 0.0,
+        }
+    }
+}
+
+impl StatusEffectKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusEffectKind::Bleeding => "Bleeding",
+            StatusEffectKind::Concussion => "Concussion",
+            StatusEffectKind::Suppression => "Suppressed",
+            StatusEffectKind::Exhaustion => "Exhausted",
+            StatusEffectKind::Stimulated => "Stimulated",
+        }
+    }
+
+    pub fn max_stacks(&self) -> u32 {
+        match self {
+            StatusEffectKind::Bleeding => 3,
+            StatusEffectKind::Suppression => 3,
+            _ => 1,
+        }
+    }
+
+    // Color of the icon shown in the HUD
+    pub fn color(&self) -> Color {
+        match self {
+            StatusEffectKind::Bleeding => Color::from_rgba(200, 30, 30, 230),
+            StatusEffectKind::Concussion => Color::from_rgba(230, 200, 60, 230),
+            StatusEffectKind::Suppression => Color::from_rgba(160, 160, 160, 230),
+            StatusEffectKind::Exhaustion => Color::from_rgba(90, 110, 200, 230),
+            StatusEffectKind::Stimulated => Color::from_rgba(70, 200, 90, 230),
+        }
+    }
+
+    // Applies the effect of every stack on top of the other effects
+    fn apply(&self, stacks: u32, modifiers: &mut StatusModifiers) {
+        let stacks = stacks as i32;
+        match self {
+            StatusEffectKind::Bleeding => {
+                modifiers.health_per_second -= 1.5 * stacks as f32;
+            }
+            StatusEffectKind::Concussion => {
+                modifiers.movement_speed *= 0.8;
+                modifiers.spread *= 1.8;
+                modifiers.vision *= 0.6;
+            }
+            StatusEffectKind::Suppression => {
+                modifiers.spread *= 1.4_f32.powi(stacks);
+                modifiers.vision *= 0.85_f32.powi(stacks);
+            }
+            StatusEffectKind::Exhaustion => {
+                modifiers.movement_speed *= 0.85;
+                modifiers.stamina_regen *= 0.7;
+            }
+            StatusEffectKind::Stimulated => {
+                modifiers.stamina_regen *= 2.5;
+                modifiers.stamina_cost *= 0.8;
+            }
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    // Adding an effect the player already has adds a stack and refreshes its duration
+    // Use f32::INFINITY for effects that only end when treated
+    pub fn add(&mut self, kind: StatusEffectKind, duration: f32) {
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => {
+                effect.stacks = (effect.stacks + 1).min(kind.max_stacks());
+                effect.time_left = effect.time_left.max(duration);
+            }
+            None => self.effects.push(StatusEffect {
+                kind,
+                stacks: 1,
+                time_left: duration,
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, kind: StatusEffectKind) {
+        self.effects.retain(|effect| effect.kind != kind);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.effects.iter()
+    }
+
+    // Counts down every effect and removes the ones that ran out
+    pub fn update(&mut self, delta: f32) {
+        for effect in &mut self.effects {
+            effect.time_left -= delta;
+        }
+        self.effects.retain(|effect| effect.time_left > 0.0);
+    }
+
+    pub fn modifiers(&self) -> StatusModifiers {
+        let mut modifiers = StatusModifiers::default();
+        for effect in &self.effects {
+            effect.kind.apply(effect.stacks, &mut modifiers);
+        }
+        modifiers
+    }
+}
@@ -1,13 +1,14 @@
 use crate::{
     assets::Assets,
     camera::GameCamera,
-    maps::TILE_COLLIDER_LOOKUP,
-    player::Player,
+    manifest::TextureId,
+    maps::{TILE_BROKEN_LOOKUP, TILE_COLLIDER_LOOKUP, TILE_LOW_COVER_LOOKUP, TILE_MATERIAL_LOOKUP},
+    materials::TileMaterial,
+    memory::VisionMemory,
     utils::draw_rect,
-    world::{ANGLE_PERIPHERAL_FACTOR, LINE_LENGTH},
 };
 use macroquad::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 pub struct LineSegment {
     pub x1: f32,
@@ -19,10 +20,77 @@ pub struct LineSegment {
 pub struct TileMap {
     pub data: Vec<u32>,
     pub collidables: HashSet<(u16, u16)>,
+    pub blocked: HashMap<(u16, u16), TileMaterial>, // Tiles blocked at runtime, like by closed doors
+    pub revision: u32, // Changes whenever collision changes at runtime, so cached light outlines are rebuilt
+    pub tile_damage: HashMap<(u16, u16), f32>, // Damage taken by destructible tiles so far
+    pub ambient_light: Color, // Light level of the map in daylight, without any lights
     pub width: u16,
     pub height: u16,
 }
 
+// Gap left between a moving rect and the collider it stops at
+const COLLISION_SKIN: f32 = 0.001;
+// Furthest a rect is nudged sideways to get around a corner it clipped
+const CORNER_TOLERANCE: f32 = 1.5;
+
+// Where a line first hits a tile that blocks it
+pub struct TileHit {
+    pub grid_x: u16,
+    pub grid_y: u16,
+    pub pos: Vec2,
+    pub normal: Vec2, // Points out of the side of the tile that was hit
+}
+
+// Where a moving rect first touches a tile
+struct SweepHit {
+    time: f32,    // Fraction of the motion travelled before touching, from 0.0 to 1.0
+    normal: Vec2, // Points out of the side of the tile that was hit
+    tile: Rect,
+}
+
+impl SweepHit {
+    // Swept AABB test between a moving rect and a static one
+    fn between(rect: Rect, motion: Vec2, tile: Rect) -> Option<SweepHit> {
+        // Time the rect starts and stops overlapping the tile along one axis
+        let axis_times = |pos: f32, size: f32, motion: f32, tile_pos: f32, tile_size: f32| {
+            if motion > 0.0 {
+                Some((
+                    (tile_pos - (pos + size)) / motion,
+                    (tile_pos + tile_size - pos) / motion,
+                ))
+            } else if motion < 0.0 {
+                Some((
+                    (tile_pos + tile_size - pos) / motion,
+                    (tile_pos - (pos + size)) / motion,
+                ))
+            } else if pos < tile_pos + tile_size && pos + size > tile_pos {
+                Some((f32::NEG_INFINITY, f32::INFINITY))
+            } else {
+                None
+            }
+        };
+        let (entry_x, exit_x) = axis_times(rect.x, rect.w, motion.x, tile.x, tile.w)?;
+        let (entry_y, exit_y) = axis_times(rect.y, rect.h, motion.y, tile.y, tile.h)?;
+
+        let entry = entry_x.max(entry_y);
+        let exit = exit_x.min(exit_y);
+        // Already overlapping tiles are ignored so the rect can always move out of them
+        if entry >= exit || !(-COLLISION_SKIN..=1.0).contains(&entry) {
+            return None;
+        }
+
+        let normal = match entry_x > entry_y {
+            true => Vec2::new(-motion.x.signum(), 0.0),
+            false => Vec2::new(0.0, -motion.y.signum()),
+        };
+        Some(SweepHit {
+            time: entry.max(0.0),
+            normal,
+            tile,
+        })
+    }
+}
+
 impl LineSegment {
     fn line_segments_intersect(&self, line2: &LineSegment) -> Option<Vec2> {
         use geo::Line;
@@ -108,62 +176,357 @@ impl LineSegment {
 
 impl TileMap {
     pub fn rect_collides_with_tile(&self, rect: Rect) -> bool {
-        for (grid_x, grid_y) in self.collidables.iter() {
-            if let Some(tile) = self.get_tile(*grid_x, *grid_y) {
-                if TILE_COLLIDER_LOOKUP
-                    .get((tile.0 - 1) as usize)
-                    .unwrap_or(&false)
-                    == &false
-                {
-                    continue;
-                }
+        for (grid_x, grid_y) in self.collidables.iter().chain(self.blocked.keys()) {
+            if !self.is_collider(*grid_x, *grid_y) {
+                continue;
+            }
 
-                let tile_rect = Rect::new(*grid_x as f32 * 8.0, *grid_y as f32 * 8.0, 8.0, 8.0);
+            let tile_rect = Rect::new(*grid_x as f32 * 8.0, *grid_y as f32 * 8.0, 8.0, 8.0);
 
-                if rect.intersect(tile_rect).is_some() {
-                    return true;
-                }
+            if rect.intersect(tile_rect).is_some() {
+                return true;
             }
         }
         false
     }
 
-    pub fn line_collides_with_tile(&self, line: &LineSegment) -> Vec<Vec2> {
-        for (grid_x, grid_y) in self.collidables.iter() {
-            if let Some(tile) = self.get_tile(*grid_x, *grid_y) {
-                if let Some(is_collider) = TILE_COLLIDER_LOOKUP.get(tile.0 as usize - 1) {
-                    let intersects = line.line_intersects_rect(Rect {
-                        x: *grid_x as f32 * 8.0,
-                        y: *grid_y as f32 * 8.0,
-                        w: 8.0,
-                        h: 8.0,
-                    });
-                    if *is_collider {
-                        if !intersects.is_empty() {
-                            dbg!(&intersects);
-                        }
-                        return intersects;
-                    }
+    // Finds where the line first hits a tile that blocks it, skipping the ignored tiles
+    pub fn line_hits_tile(&self, from: Vec2, to: Vec2, ignored: &[(u16, u16)]) -> Option<TileHit> {
+        let (grid_x, grid_y) = self
+            .walk_line(from, to, |x, y| {
+                x < 0
+                    || y < 0
+                    || (self.is_collider(x as u16, y as u16)
+                        && !ignored.contains(&(x as u16, y as u16)))
+            })
+            .map(|(x, y)| (x.max(0) as u16, y.max(0) as u16))?;
+        let (pos, normal) = TileMap::line_enters_tile(from, to, grid_x, grid_y);
+        Some(TileHit {
+            grid_x,
+            grid_y,
+            pos,
+            normal,
+        })
+    }
+
+    // Where the line enters the tile and the normal of the face it goes through
+    fn line_enters_tile(from: Vec2, to: Vec2, grid_x: u16, grid_y: u16) -> (Vec2, Vec2) {
+        let tile = Rect::new(grid_x as f32 * 8.0, grid_y as f32 * 8.0, 8.0, 8.0);
+        let point = Rect::new(from.x, from.y, 0.0, 0.0);
+        match SweepHit::between(point, to - from, tile) {
+            Some(hit) => (from + (to - from) * hit.time, hit.normal),
+            // The line started inside of the tile
+            None => (from, (from - to).normalize_or_zero()),
+        }
+    }
+
+    pub fn material(&self, grid_x: u16, grid_y: u16) -> TileMaterial {
+        if let Some(material) = self.blocked.get(&(grid_x, grid_y)) {
+            return *material;
+        }
+        match self.get_tile(grid_x, grid_y) {
+            Some((tile_id, ..)) if grid_x < self.width => *TILE_MATERIAL_LOOKUP
+                .get((tile_id as usize).wrapping_sub(1))
+                .unwrap_or(&TileMaterial::Ground),
+            _ => TileMaterial::Ground,
+        }
+    }
+
+    // Damages a destructible tile, swapping it for its broken tile once it runs out of hit points
+    // Returns true if the tile was destroyed
+    // Whatever blocks the tile at runtime takes care of its own damage
+    pub fn damage_tile(&mut self, grid_x: u16, grid_y: u16, damage: f32) -> bool {
+        if self.blocked.contains_key(&(grid_x, grid_y)) {
+            return false;
+        }
+        let Some(hit_points) = self.material(grid_x, grid_y).hit_points() else {
+            return false;
+        };
+        let damage_taken = self.tile_damage.entry((grid_x, grid_y)).or_insert(0.0);
+        *damage_taken += damage;
+        if *damage_taken < hit_points {
+            return false;
+        }
+        self.tile_damage.remove(&(grid_x, grid_y));
+
+        // Flip and rotate bits are kept so the broken tile lines up with its neighbours
+        let index = (grid_x + grid_y * self.width) as usize;
+        let broken_id = TILE_BROKEN_LOOKUP[(self.data[index] & 0x1FFFFFFF) as usize - 1];
+        self.data[index] = (self.data[index] & !0x1FFFFFFF) | broken_id;
+        match TILE_COLLIDER_LOOKUP[broken_id as usize - 1] {
+            true => self.collidables.insert((grid_x, grid_y)),
+            false => self.collidables.remove(&(grid_x, grid_y)),
+        };
+        self.revision = self.revision.wrapping_add(1);
+        true
+    }
+
+    // Checks if the tile at the grid position blocks movement, bullets and sight
+    // Anything outside of the map is treated as a wall
+    pub fn is_collider(&self, grid_x: u16, grid_y: u16) -> bool {
+        if grid_x >= self.width
+            || grid_y >= self.height
+            || self.blocked.contains_key(&(grid_x, grid_y))
+        {
+            return true;
+        }
+        match self.get_tile(grid_x, grid_y) {
+            Some((tile_id, ..)) => *TILE_COLLIDER_LOOKUP
+                .get((tile_id as usize).wrapping_sub(1))
+                .unwrap_or(&false),
+            None => true,
+        }
+    }
+
+    // Blocks a tile at runtime with something made of the material, None unblocks it
+    // Used by map objects like doors
+    pub fn set_blocked(&mut self, grid_x: u16, grid_y: u16, material: Option<TileMaterial>) {
+        let was_blocked = match material {
+            Some(material) => self.blocked.insert((grid_x, grid_y), material).is_some(),
+            None => self.blocked.remove(&(grid_x, grid_y)).is_some(),
+        };
+        if was_blocked != material.is_some() {
+            self.revision = self.revision.wrapping_add(1);
+        }
+    }
+
+    // Same as is_collider, but for grid positions that can be outside of the map
+    fn is_collider_at(&self, grid_x: i32, grid_y: i32) -> bool {
+        grid_x < 0 || grid_y < 0 || self.is_collider(grid_x as u16, grid_y as u16)
+    }
+
+    // Checks if the rect overlaps any collider, touching a collider doesn't count
+    pub fn rect_overlaps_collider(&self, rect: Rect) -> bool {
+        let (min_x, max_x) = TileMap::grid_span(rect.x, rect.x + rect.w);
+        let (min_y, max_y) = TileMap::grid_span(rect.y, rect.y + rect.h);
+        (min_y..=max_y).any(|y| (min_x..=max_x).any(|x| self.is_collider_at(x, y)))
+    }
+
+    // Grid cells covered from start to end along one axis, edges lying on a grid line don't count
+    fn grid_span(start: f32, end: f32) -> (i32, i32) {
+        ((start / 8.0).floor() as i32, (end / 8.0).ceil() as i32 - 1)
+    }
+
+    // Moves the rect by the motion, stopping at contact with colliders and sliding along them
+    // Returns the motion the rect actually made
+    pub fn sweep_rect(&self, rect: Rect, motion: Vec2) -> Vec2 {
+        const MAX_ITERATIONS: usize = 4;
+
+        let mut rect = rect;
+        let mut remaining = motion;
+        let mut moved = Vec2::ZERO;
+
+        for _ in 0..MAX_ITERATIONS {
+            if remaining == Vec2::ZERO {
+                break;
+            }
+            let Some(hit) = self.first_sweep_hit(rect, remaining) else {
+                moved += remaining;
+                break;
+            };
+
+            // Move up to the collider, leaving a tiny gap so the rect never overlaps it
+            let axis_motion = remaining.dot(hit.normal).abs();
+            let time = (hit.time - COLLISION_SKIN / axis_motion).max(0.0);
+            let step = remaining * time;
+            rect = rect.offset(step);
+            moved += step;
+            remaining -= step;
+
+            // Nudge around corners that were only just clipped instead of stopping dead
+            // The nudge uses up the motion, so big nudges are spread over several frames
+            if let Some(nudge) = self.corner_nudge(rect, remaining, &hit) {
+                let length = remaining.length();
+                if nudge.length() > length {
+                    moved += nudge.normalize() * length;
+                    break;
                 }
+                rect = rect.offset(nudge);
+                moved += nudge;
+                remaining = remaining.normalize() * (length - nudge.length());
+                continue;
             }
+
+            // Slide along the surface with what's left of the motion
+            remaining -= hit.normal * remaining.dot(hit.normal);
         }
-        vec![]
+        moved
+    }
+
+    // Finds the earliest collider the rect hits while moving, using swept AABB tests
+    fn first_sweep_hit(&self, rect: Rect, motion: Vec2) -> Option<SweepHit> {
+        let swept = rect.combine_with(rect.offset(motion));
+        let (min_x, max_x) = TileMap::grid_span(swept.x, swept.x + swept.w);
+        let (min_y, max_y) = TileMap::grid_span(swept.y, swept.y + swept.h);
+
+        (min_y..=max_y)
+            .flat_map(|grid_y| (min_x..=max_x).map(move |grid_x| (grid_x, grid_y)))
+            .filter(|&(grid_x, grid_y)| self.is_collider_at(grid_x, grid_y))
+            .filter_map(|(grid_x, grid_y)| {
+                let tile = Rect::new(grid_x as f32 * 8.0, grid_y as f32 * 8.0, 8.0, 8.0);
+                SweepHit::between(rect, motion, tile)
+            })
+            .min_by(|a, b| a.time.total_cmp(&b.time))
+    }
+
+    // Sideways offset that lets the rect slip past the corner of the tile it hit
+    // Only used when the rect overlaps the tile by less than CORNER_TOLERANCE sideways
+    fn corner_nudge(&self, rect: Rect, remaining: Vec2, hit: &SweepHit) -> Option<Vec2> {
+        let side = hit.normal.perp();
+        let forward = -hit.normal;
+
+        // Corners are only nudged around when moving mostly straight into them
+        if remaining.dot(forward) <= remaining.dot(side).abs() {
+            return None;
+        }
+
+        // How far the rect overlaps the tile on each side of it, along the sideways axis
+        let (rect_min, rect_max) = match side.x != 0.0 {
+            true => (rect.x, rect.x + rect.w),
+            false => (rect.y, rect.y + rect.h),
+        };
+        let (tile_min, tile_max) = match side.x != 0.0 {
+            true => (hit.tile.x, hit.tile.x + hit.tile.w),
+            false => (hit.tile.y, hit.tile.y + hit.tile.h),
+        };
+        let side = side.abs();
+        let nudge = if tile_max - rect_min <= CORNER_TOLERANCE {
+            side * (tile_max - rect_min + COLLISION_SKIN)
+        } else if rect_max - tile_min <= CORNER_TOLERANCE {
+            -side * (rect_max - tile_min + COLLISION_SKIN)
+        } else {
+            return None;
+        };
+
+        // The nudged rect needs room both where it ends up and in front of it
+        let nudged = rect.offset(nudge);
+        let ahead = nudged.offset(forward * COLLISION_SKIN * 2.0);
+        match self.rect_overlaps_collider(nudged) || self.rect_overlaps_collider(ahead) {
+            true => None,
+            false => Some(nudge),
+        }
+    }
+
+    // Walks every tile the line passes through, in order, until `stop` returns true
+    // Returns the tile it stopped at
+    fn walk_line(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        mut stop: impl FnMut(i32, i32) -> bool,
+    ) -> Option<(i32, i32)> {
+        let (start, end) = (from / 8.0, to / 8.0);
+        let dir = end - start;
+        let (mut x, mut y) = (start.x.floor() as i32, start.y.floor() as i32);
+        let (end_x, end_y) = (end.x.floor() as i32, end.y.floor() as i32);
+
+        let step_x = if dir.x < 0.0 { -1 } else { 1 };
+        let step_y = if dir.y < 0.0 { -1 } else { 1 };
+
+        // Distance along the line (0.0 to 1.0) needed to cross one tile
+        let delta_x = (1.0 / dir.x).abs();
+        let delta_y = (1.0 / dir.y).abs();
+
+        // Distance along the line to the next tile boundary
+        let mut next_x = match dir.x > 0.0 {
+            true => (x as f32 + 1.0 - start.x) * delta_x,
+            false => (start.x - x as f32) * delta_x,
+        };
+        let mut next_y = match dir.y > 0.0 {
+            true => (y as f32 + 1.0 - start.y) * delta_y,
+            false => (start.y - y as f32) * delta_y,
+        };
+
+        loop {
+            if stop(x, y) {
+                return Some((x, y));
+            }
+            if x == end_x && y == end_y {
+                return None;
+            }
+            if next_x < next_y {
+                if next_x > 1.0 {
+                    return None;
+                }
+                x += step_x;
+                next_x += delta_x;
+            } else {
+                if next_y > 1.0 {
+                    return None;
+                }
+                y += step_y;
+                next_y += delta_y;
+            }
+        }
+    }
+
+    // Returns the first tile along the line that blocks it
+    pub fn first_collider_on_line(&self, from: Vec2, to: Vec2) -> Option<(u16, u16)> {
+        self.walk_line(from, to, |x, y| {
+            x < 0 || y < 0 || self.is_collider(x as u16, y as u16)
+        })
+        .map(|(x, y)| (x.max(0) as u16, y.max(0) as u16))
+    }
+
+    // Counts the walls along the line, a run of blocking tiles is one wall however thick it is
+    pub fn walls_on_line(&self, from: Vec2, to: Vec2) -> u32 {
+        let mut walls = 0;
+        let mut in_wall = false;
+        self.walk_line(from, to, |x, y| {
+            let is_collider = x >= 0 && y >= 0 && self.is_collider(x as u16, y as u16);
+            if is_collider && !in_wall {
+                walls += 1;
+            }
+            in_wall = is_collider;
+            false
+        });
+        walls
+    }
+
+    // Low cover only stops bullets aimed at crouching or prone players
+    pub fn is_low_cover(&self, grid_x: u16, grid_y: u16) -> bool {
+        match self.get_tile(grid_x, grid_y) {
+            Some((tile_id, ..)) if grid_x < self.width => *TILE_LOW_COVER_LOOKUP
+                .get((tile_id as usize).wrapping_sub(1))
+                .unwrap_or(&false),
+            _ => false,
+        }
+    }
+
+    // Finds where the line first crosses into low cover
+    pub fn line_hits_low_cover(&self, from: Vec2, to: Vec2) -> Option<Vec2> {
+        self.walk_line(from, to, |x, y| {
+            x >= 0 && y >= 0 && self.is_low_cover(x as u16, y as u16)
+        })
+        .map(|(x, y)| TileMap::line_enters_tile(from, to, x as u16, y as u16).0)
+    }
+
+    pub fn has_line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        self.first_collider_on_line(from, to).is_none()
     }
 
     // Returns (tile_id, flip_x, flip_y, rotate)
     pub fn get_tile(&self, grid_x: u16, grid_y: u16) -> Option<(u32, bool, bool, bool)> {
+        self.tile_data(grid_x, grid_y).map(TileMap::decode_tile)
+    }
+
+    // Raw tile data, the tile id with the flip and rotate bits
+    pub fn tile_data(&self, grid_x: u16, grid_y: u16) -> Option<u32> {
         self.data
             .get((grid_x + grid_y * self.width) as usize)
-            .map(|tile| {
-                (
-                    tile & 0x1FFFFFFF, // Get all bits except top three, which returns the tile id
-                    (tile & 0x80000000) != 0, // Get most significant bit for flip_x
-                    (tile & 0x40000000) != 0, // Get second bit bit for flip_y
-                    (tile & 0x20000000) != 0, // Get third bit for rotate
-                )
-            })
+            .copied()
     }
 
+    fn decode_tile(tile: u32) -> (u32, bool, bool, bool) {
+        (
+            tile & 0x1FFFFFFF, // Get all bits except top three, which returns the tile id
+            (tile & 0x80000000) != 0, // Get most significant bit for flip_x
+            (tile & 0x40000000) != 0, // Get second bit bit for flip_y
+            (tile & 0x20000000) != 0, // Get third bit for rotate
+        )
+    }
+
+    // Old ray based vision, only kept around to benchmark shadowcasting against
     pub fn find_tiles(&self, angles: Vec<f32>, length: f32, origin: Vec2) -> HashSet<(u16, u16)> {
         let mut tiles = HashSet::with_capacity(700);
         for angle in angles {
@@ -202,8 +565,7 @@ impl TileMap {
         while reached_length < length {
             let tile_x = x as u16;
             let tile_y = y as u16;
-            let tile_id = self.data[(tile_x + tile_y * self.width) as usize] & 0x1FFFFFFF;
-            if TILE_COLLIDER_LOOKUP[((tile_id) - 1) as usize] {
+            if self.is_collider(tile_x, tile_y) {
                 break;
             }
             tiles.push((tile_x, tile_y));
@@ -221,43 +583,19 @@ impl TileMap {
         tiles
     }
 
-    pub fn draw(&self, assets: &Assets, player: &Player, camera: &GameCamera) {
-        // Calculate tiles that are visible to the rays
-        let angles = player.get_player_rays(
-            std::f32::consts::PI * ANGLE_PERIPHERAL_FACTOR,
-            LINE_LENGTH * ANGLE_PERIPHERAL_FACTOR,
-        );
-        let visible_tiles = self.find_tiles(
-            angles,
-            LINE_LENGTH / 8.0 * ANGLE_PERIPHERAL_FACTOR,
-            player.pos,
-        );
-
-        // Render
-        self.draw_tiles(assets, visible_tiles, camera);
-    }
-
-    fn draw_tiles(
-        &self,
-        assets: &Assets,
-        visible_to_player: HashSet<(u16, u16)>,
-        camera: &GameCamera,
-    ) {
+    // Tiles are drawn fully lit, shadows are drawn over them afterwards
+    // Tiles the player remembers are drawn the way they were when last seen
+    pub fn draw(&self, assets: &Assets, camera: &GameCamera, memory: &VisionMemory) {
         const FIT_OFFSET: f32 = 0.25;
-        const VISIBLE_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0);
-        const NOT_VISIBLE_TILE_COLOR: Color = Color::new(0.83, 0.83, 0.83, 1.0);
-
-        let visible_to_camera = camera.get_visible_tiles(self);
 
-        for (grid_x, grid_y) in visible_to_camera.iter() {
-            let (tile_id, flip_x, flip_y, rotate) = match self.get_tile(*grid_x, *grid_y) {
-                Some(tile) => tile,
+        for (grid_x, grid_y) in camera.get_visible_tiles(self).iter() {
+            let tile = memory
+                .remembered_tile(*grid_x, *grid_y)
+                .or_else(|| self.tile_data(*grid_x, *grid_y));
+            let (tile_id, flip_x, flip_y, rotate) = match tile {
+                Some(tile) => TileMap::decode_tile(tile),
                 None => continue,
             };
-            let color = match visible_to_player.contains(&(*grid_x, *grid_y)) {
-                true => VISIBLE_COLOR,
-                false => NOT_VISIBLE_TILE_COLOR,
-            };
 
             let mut draw_params = DrawTextureParams {
                 source: Some(Rect::new(
@@ -286,10 +624,10 @@ impl TileMap {
             }
 
             draw_texture_ex(
-                &assets.get_texture("tiles.png"),
+                &assets.get_texture(TextureId::Tiles),
                 *grid_x as f32 * 8.0,
                 *grid_y as f32 * 8.0,
-                color, // Make into shadow render later
+                WHITE,
                 draw_params,
             );
         }
@@ -297,28 +635,130 @@ impl TileMap {
 
     pub fn draw_collidables(&self, camera: &GameCamera) {
         for (grid_x, grid_y) in camera.get_visible_tiles(self) {
-            let tile = self.get_tile(grid_x, grid_y);
-            if let Some(tile) = tile {
-                if TILE_COLLIDER_LOOKUP[tile.0 as usize - 1] {
-                    draw_rect(
-                        Rect::new(grid_x as f32 * 8.0, grid_y as f32 * 8.0, 8.0, 8.0),
-                        Color::new(1.0, 0.0, 0.3, 0.75),
-                    );
-                    let rect = Rect {
-                        x: grid_x as f32 * 8.0,
-                        y: grid_y as f32 * 8.0,
-                        w: 8.0,
-                        h: 8.0,
-                    };
-
-                    let (left, right, top, bottom) = LineSegment::from_rect(&rect);
-                    let color = ORANGE;
-                    left.draw(color);
-                    right.draw(color);
-                    top.draw(color);
-                    bottom.draw(color);
-                }
+            if self.is_collider(grid_x, grid_y) {
+                draw_rect(
+                    Rect::new(grid_x as f32 * 8.0, grid_y as f32 * 8.0, 8.0, 8.0),
+                    Color::new(1.0, 0.0, 0.3, 0.75),
+                );
+                let rect = Rect {
+                    x: grid_x as f32 * 8.0,
+                    y: grid_y as f32 * 8.0,
+                    w: 8.0,
+                    h: 8.0,
+                };
+
+                let (left, right, top, bottom) = LineSegment::from_rect(&rect);
+                let color = ORANGE;
+                left.draw(color);
+                right.draw(color);
+                top.draw(color);
+                bottom.draw(color);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLOOR: u32 = 1;
+    const WALL: u32 = 2;
+
+    // Square map from rows of '#' for walls and '.' for floor
+    fn map(rows: &[&str]) -> TileMap {
+        TileMap {
+            data: rows
+                .iter()
+                .flat_map(|row| row.chars())
+                .map(|tile| match tile {
+                    '#' => WALL,
+                    _ => FLOOR,
+                })
+                .collect(),
+            collidables: HashSet::new(),
+            blocked: HashMap::new(),
+            revision: 0,
+            tile_damage: HashMap::new(),
+            ambient_light: WHITE,
+            width: rows[0].len() as u16,
+            height: rows.len() as u16,
+        }
+        .generate_collidables()
+    }
+
+    fn assert_near(value: f32, expected: f32) {
+        assert!(
+            (value - expected).abs() < 0.01,
+            "expected {expected}, got {value}"
+        );
+    }
+
+    #[test]
+    fn contact_stops_at_wall_face() {
+        let tile_map = map(&["......", "......", "...#..", "......", "......", "......"]);
+        let rect = Rect::new(4.0, 18.0, 4.0, 4.0);
+
+        let moved = tile_map.sweep_rect(rect, vec2(30.0, 0.0));
+
+        // The wall's left face is at x 24
+        assert_near(rect.right() + moved.x, 24.0);
+        assert!(rect.right() + moved.x <= 24.0);
+        assert_eq!(moved.y, 0.0);
+        assert!(!tile_map.rect_overlaps_collider(rect.offset(moved)));
+    }
+
+    #[test]
+    fn motion_along_wall_slides() {
+        let tile_map = map(&["...#..", "...#..", "...#..", "...#..", "...#..", "...#.."]);
+        let rect = Rect::new(10.0, 10.0, 4.0, 4.0);
+
+        let moved = tile_map.sweep_rect(rect, vec2(20.0, 10.0));
+
+        // Stops at the wall sideways, but keeps all of the motion along it
+        assert_near(rect.right() + moved.x, 24.0);
+        assert_near(moved.y, 10.0);
+        assert!(!tile_map.rect_overlaps_collider(rect.offset(moved)));
+    }
+
+    #[test]
+    fn clipped_corner_is_nudged_past() {
+        let tile_map = map(&["......", "...#..", "......", "......", "......", "......"]);
+
+        // Overlaps the bottom of the wall by 1.0, within CORNER_TOLERANCE
+        let rect = Rect::new(16.0, 15.0, 4.0, 4.0);
+        let moved = tile_map.sweep_rect(rect, vec2(20.0, 0.0));
+        assert!(rect.x + moved.x > 32.0, "didn't get past the wall");
+        assert!(moved.y > 1.0 && moved.y <= CORNER_TOLERANCE);
+        assert!(!tile_map.rect_overlaps_collider(rect.offset(moved)));
+
+        // Overlapping by more than the tolerance stops at the wall
+        let rect = Rect::new(16.0, 13.0, 4.0, 4.0);
+        let moved = tile_map.sweep_rect(rect, vec2(20.0, 0.0));
+        assert_near(rect.right() + moved.x, 24.0);
+        assert_eq!(moved.y, 0.0);
+    }
+
+    #[test]
+    fn large_motion_does_not_tunnel() {
+        let tile_map = map(&["...#..", "...#..", "...#..", "...#..", "...#..", "...#.."]);
+
+        // A whole second of movement in one frame, many times the wall's thickness
+        let rect = Rect::new(4.0, 20.0, 4.0, 4.0);
+        let moved = tile_map.sweep_rect(rect, vec2(200.0, 0.0));
+        assert_near(rect.right() + moved.x, 24.0);
+
+        let rect = Rect::new(40.0, 20.0, 4.0, 4.0);
+        let moved = tile_map.sweep_rect(rect, vec2(-200.0, 0.0));
+        assert_near(rect.x + moved.x, 32.0);
+    }
+
+    #[test]
+    fn thick_walls_count_once() {
+        let tile_map = map(&["......", "......", ".##.#.", "......", "......", "......"]);
+
+        assert_eq!(tile_map.walls_on_line(vec2(4.0, 20.0), vec2(28.0, 20.0)), 1);
+        assert_eq!(tile_map.walls_on_line(vec2(4.0, 20.0), vec2(44.0, 20.0)), 2);
+        assert_eq!(tile_map.walls_on_line(vec2(4.0, 4.0), vec2(44.0, 4.0)), 0);
+    }
+}
@@ -1,4 +1,15 @@
-use crate::{camera::GameCamera, player::*, tile_map::TileMap, utils::is_windows};
+use crate::{
+    assets::Assets,
+    audio::{Bus, Mixer, AUDIO_SETTINGS_PATH},
+    camera::GameCamera,
+    inventory::{Encumbrance, Inventory, InventorySlot, ItemStack},
+    items::Item,
+    loot::Container,
+    noise::{HeardNoise, NoiseKind},
+    player::*,
+    tile_map::TileMap,
+    utils::is_windows,
+};
 use macroquad::prelude::*;
 use std::collections::VecDeque;
 
@@ -102,6 +113,70 @@ pub fn render_ui(player: &Player) {
     draw_rect(&filled_stamina_bar, stamina_bar_color);
     draw_rect_lines(&stamina_bar, 3.0, BLACK);
 
+    // Progress of the consumable being used
+    if let Some(using) = &player.using {
+        let use_bar = Rect {
+            x: (screen_width() - health_bar_w) / 2.0,
+            y: screen_height() * 0.6,
+            w: health_bar_w,
+            h: health_bar_h * 0.6,
+        };
+        let mut filled_use_bar = use_bar;
+        filled_use_bar.w *= using.progress();
+        draw_rect(&filled_use_bar, Color::from_rgba(230, 230, 230, 200));
+        draw_rect_lines(&use_bar, 3.0, BLACK);
+        draw_text(
+            &format!("Using {}", using.item.name),
+            use_bar.x,
+            use_bar.y - 6.0,
+            24.0,
+            WHITE,
+        );
+    }
+
+    // Carried weight next to the stamina bar
+    let encumbrance = player.inventory.encumbrance();
+    draw_text(
+        &format!(
+            "{:.1} kg ({})",
+            player.inventory.total_weight(),
+            encumbrance.name()
+        ),
+        stamina_bar.x + stamina_bar.w + 12.0,
+        stamina_bar.y + stamina_bar.h * 0.8,
+        24.0,
+        encumbrance_color(encumbrance),
+    );
+
+    // Status effect icons above the health bar
+    let icon_size = 34.0;
+    for (index, effect) in player.status_effects.iter().enumerate() {
+        let icon = Rect {
+            x: health_bar.x + (icon_size + 6.0) * index as f32,
+            y: health_bar.y - icon_size - 10.0,
+            w: icon_size,
+            h: icon_size,
+        };
+        draw_rect(&icon, effect.kind.color());
+        draw_rect_lines(&icon, 2.0, BLACK);
+        draw_text(
+            &effect.kind.name()[..1],
+            icon.x + 9.0,
+            icon.y + 25.0,
+            30.0,
+            BLACK,
+        );
+        if effect.stacks > 1 {
+            draw_text(
+                &effect.stacks.to_string(),
+                icon.x + icon.w - 9.0,
+                icon.y + icon.h - 2.0,
+                18.0,
+                WHITE,
+            );
+        }
+    }
+
     let (primary_rect_w, primary_rect_h) = (225.0, 65.0);
     let gun_backdrop = Color::new(0.15, 0.15, 0.15, 0.65);
     let buffer = 12.0;
@@ -123,6 +198,15 @@ pub fn render_ui(player: &Player) {
     draw_rect(&secondary_gun_rect, gun_backdrop);
 }
 
+fn encumbrance_color(encumbrance: Encumbrance) -> Color {
+    match encumbrance {
+        Encumbrance::Light => WHITE,
+        Encumbrance::Medium => Color::from_rgba(240, 220, 120, 255),
+        Encumbrance::Heavy => Color::from_rgba(240, 150, 60, 255),
+        Encumbrance::Overloaded => Color::from_rgba(255, 60, 60, 255),
+    }
+}
+
 pub fn render_debug_ui(player: &Player, camera: &GameCamera, world: &TileMap) {
     let text_size = 45.0;
     let mut ui_stack = vec![];
@@ -157,6 +241,16 @@ pub fn render_debug_ui(player: &Player, camera: &GameCamera, world: &TileMap) {
 
     ui_stack.push(format!("Health: {}", player.health.round()));
     ui_stack.push(format!("Stamina: {}", player.stamina.round()));
+    ui_stack.push(format!(
+        "Armor: {} ({}%)",
+        player.inventory.clothes.durability.round(),
+        (player.inventory.clothes.effective_damage_reduction() * 100.0).round()
+    ));
+    ui_stack.push(format!(
+        "Stance: {} (noise {}%)",
+        player.stance.name(),
+        (player.stance.noise_multiplier() * 100.0).round()
+    ));
     ui_stack.push(format!("Player Pos: {}", (player.pos / 8.0).floor()));
     ui_stack.push(format!(
         "Aiming at: {}",
@@ -178,3 +272,457 @@ pub fn render_debug_ui(player: &Player, camera: &GameCamera, world: &TileMap) {
         );
     }
 }
+
+pub enum InventoryAction {
+    Drop(ItemStack),
+    Use(Item::Consumable),
+}
+
+// Inventory screen with drag and drop between the grid and equipment slots
+pub struct InventoryScreen {
+    dragging: Option<InventorySlot>,
+}
+
+impl InventoryScreen {
+    const CELL_SIZE: f32 = 64.0;
+    const CELL_GAP: f32 = 6.0;
+    const EQUIPMENT_SLOTS: [(InventorySlot, &'static str); 5] = [
+        (InventorySlot::Primary, "Primary"),
+        (InventorySlot::Secondary, "Secondary"),
+        (InventorySlot::Clothes, "Clothes"),
+        (InventorySlot::Backpack, "Backpack"),
+        (InventorySlot::Throwable, "Throwable"),
+    ];
+
+    pub fn new() -> Self {
+        Self { dragging: None }
+    }
+
+    // Screen space rects of every slot, equipment on the left and the grid on the right
+    fn slot_rects(inventory: &Inventory) -> Vec<(InventorySlot, Rect)> {
+        let step = Self::CELL_SIZE + Self::CELL_GAP;
+        let total_w = step * (inventory.grid_width + 2) as f32;
+        let top_left = vec2(
+            (screen_width() - total_w) / 2.0,
+            screen_height() / 2.0 - step * 2.5,
+        );
+
+        let mut rects = vec![];
+        for (index, (slot, _)) in Self::EQUIPMENT_SLOTS.iter().enumerate() {
+            rects.push((
+                *slot,
+                Rect::new(
+                    top_left.x,
+                    top_left.y + step * index as f32,
+                    Self::CELL_SIZE,
+                    Self::CELL_SIZE,
+                ),
+            ));
+        }
+        for index in 0..inventory.grid.len() {
+            let (x, y) = (index % inventory.grid_width, index / inventory.grid_width);
+            rects.push((
+                InventorySlot::Grid(index),
+                Rect::new(
+                    top_left.x + step * (x + 2) as f32,
+                    top_left.y + step * y as f32,
+                    Self::CELL_SIZE,
+                    Self::CELL_SIZE,
+                ),
+            ));
+        }
+        rects
+    }
+
+    // Area covered by all the slots, items released outside of it are dropped
+    fn panel_rect(inventory: &Inventory) -> Rect {
+        let slots = Self::slot_rects(inventory)
+            .into_iter()
+            .map(|(_, rect)| rect)
+            .reduce(|panel, rect| panel.combine_with(rect))
+            .unwrap_or_default();
+        // Padded more vertically to fit the slot labels
+        let padding = vec2(Self::CELL_GAP, Self::CELL_GAP * 4.0);
+        Rect::new(
+            slots.x - padding.x,
+            slots.y - padding.y,
+            slots.w + padding.x * 2.0,
+            slots.h + padding.y * 2.0,
+        )
+    }
+
+    fn slot_at(inventory: &Inventory, pos: Vec2) -> Option<InventorySlot> {
+        Self::slot_rects(inventory)
+            .into_iter()
+            .find(|(_, rect)| rect.contains(pos))
+            .map(|(slot, _)| slot)
+    }
+
+    // Returns what the player did with an item that the rest of the game has to handle
+    pub fn update(&mut self, inventory: &mut Inventory) -> Option<InventoryAction> {
+        if is_key_pressed(KeyCode::Tab) {
+            inventory.is_open = !inventory.is_open;
+            self.dragging = None;
+        }
+        if !inventory.is_open {
+            return None;
+        }
+
+        let mouse_pos: Vec2 = mouse_position().into();
+        if is_mouse_button_pressed(MouseButton::Left) {
+            self.dragging =
+                Self::slot_at(inventory, mouse_pos).filter(|slot| inventory.peek(*slot).is_some());
+        }
+        // Right clicking a consumable uses it
+        if is_mouse_button_pressed(MouseButton::Right) {
+            let clicked = Self::slot_at(inventory, mouse_pos).and_then(|slot| inventory.peek(slot));
+            if let Some(Item::Kind::Consumable(consumable)) = clicked.map(|stack| stack.item) {
+                return Some(InventoryAction::Use(consumable));
+            }
+        }
+        if is_mouse_button_released(MouseButton::Left) {
+            if let Some(from) = self.dragging.take() {
+                match Self::slot_at(inventory, mouse_pos) {
+                    Some(to) => {
+                        inventory.move_item(from, to);
+                    }
+                    None if !Self::panel_rect(inventory).contains(mouse_pos) => {
+                        return inventory.take(from).map(InventoryAction::Drop);
+                    }
+                    None => {}
+                }
+            }
+        }
+        None
+    }
+
+    pub fn draw(&self, inventory: &Inventory, assets: &Assets) {
+        if !inventory.is_open {
+            return;
+        }
+        let slot_color = Color::new(0.15, 0.15, 0.15, 0.75);
+        let equipment_color = Color::new(0.22, 0.2, 0.15, 0.75);
+        let text_size = 20.0;
+
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.4),
+        );
+
+        // Weight above the grid
+        let panel = Self::panel_rect(inventory);
+        let encumbrance = inventory.encumbrance();
+        draw_text(
+            &format!(
+                "Weight: {:.1} kg ({})",
+                inventory.total_weight(),
+                encumbrance.name()
+            ),
+            panel.x,
+            panel.y - 4.0,
+            text_size * 1.3,
+            encumbrance_color(encumbrance),
+        );
+
+        for (slot, rect) in Self::slot_rects(inventory) {
+            let color = match slot {
+                InventorySlot::Grid(_) => slot_color,
+                _ => equipment_color,
+            };
+            draw_rect(&rect, color);
+            draw_rect_lines(&rect, 2.0, BLACK);
+
+            if let Some((_, label)) = Self::EQUIPMENT_SLOTS.iter().find(|(s, _)| *s == slot) {
+                draw_text(label, rect.x, rect.y - 2.0, text_size, WHITE);
+            }
+
+            if self.dragging == Some(slot) {
+                continue;
+            }
+            if let Some(stack) = inventory.peek(slot) {
+                Self::draw_stack(assets, &stack, rect, text_size);
+            }
+        }
+
+        // Draw the item being dragged under the mouse
+        if let Some(stack) = self.dragging.and_then(|slot| inventory.peek(slot)) {
+            let (mouse_x, mouse_y) = mouse_position();
+            let rect = Rect::new(
+                mouse_x - Self::CELL_SIZE / 2.0,
+                mouse_y - Self::CELL_SIZE / 2.0,
+                Self::CELL_SIZE,
+                Self::CELL_SIZE,
+            );
+            Self::draw_stack(assets, &stack, rect, text_size);
+        }
+    }
+
+    fn draw_stack(assets: &Assets, stack: &ItemStack, rect: Rect, text_size: f32) {
+        draw_texture_ex(
+            &assets.get_texture(stack.item.texture()),
+            rect.x,
+            rect.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(rect.w, rect.h)),
+                ..Default::default()
+            },
+        );
+        // Armor durability bar along the bottom of the cell
+        if let Item::Kind::Clothes(clothes) = &stack.item {
+            if clothes.max_durability > 0.0 {
+                let bar = Rect::new(rect.x + 4.0, rect.y + rect.h - 8.0, rect.w - 8.0, 4.0);
+                let mut filled_bar = bar;
+                filled_bar.w *= clothes.durability / clothes.max_durability;
+                draw_rect(&bar, Color::new(0.0, 0.0, 0.0, 0.6));
+                draw_rect(&filled_bar, Color::new(0.3, 0.7, 1.0, 0.9));
+            }
+        }
+        if stack.count > 1 {
+            draw_text(
+                &stack.count.to_string(),
+                rect.x + rect.w - text_size / 2.0,
+                rect.y + rect.h - 4.0,
+                text_size,
+                WHITE,
+            );
+        }
+    }
+}
+
+// Arrows at the edge of the screen pointing at noises the player heard but couldn't see
+pub fn render_sound_indicators(player: &Player, camera: &GameCamera) {
+    let margin = 40.0;
+    let center = camera.world_to_screen(player.pos);
+    let half_size = Vec2::new(screen_width(), screen_height()) / 2.0 - margin;
+
+    for heard in player.heard.iter().filter(|heard| !heard.in_sight) {
+        let Some(direction) = (camera.world_to_screen(heard.pos) - center).try_normalize() else {
+            continue;
+        };
+        // Pushed out from the middle of the screen until it reaches the margin
+        let screen_center = Vec2::new(screen_width(), screen_height()) / 2.0;
+        let scale = (half_size / direction.abs()).min_element();
+        let tip = screen_center + direction * scale;
+
+        let size = 12.0 + heard.loudness * 12.0;
+        let side = direction.perp() * size * 0.6;
+        let base = tip - direction * size;
+        let fade = heard.time_left / HeardNoise::MEMORY_TIME;
+        let alpha = fade * (0.35 + heard.loudness).min(1.0);
+        // Gunfire and explosions stand out from quieter noises
+        let color = match heard.kind {
+            NoiseKind::Gunshot | NoiseKind::Explosion => Color::new(1.0, 0.45, 0.3, alpha),
+            NoiseKind::Footstep | NoiseKind::WeaponSwitch => Color::new(1.0, 0.9, 0.6, alpha),
+        };
+        draw_triangle(tip, base + side, base - side, color);
+    }
+}
+
+pub fn render_pickup_prompt(item_name: &str) {
+    render_interact_prompt(&format!("Pick up {}", item_name.replace('_', " ")));
+}
+
+pub fn render_interact_prompt(action: &str) {
+    let text_size = 30.0;
+    let text = format!("[E] {action}");
+    let text_dimensions = measure_text(&text, None, text_size as u16, 1.0);
+    draw_text(
+        &text,
+        (screen_width() - text_dimensions.width) / 2.0,
+        screen_height() * 0.7,
+        text_size,
+        WHITE,
+    );
+}
+
+// Panel listing the items of the container being searched
+// Clicking a revealed item moves it to the inventory
+pub struct ContainerScreen;
+
+impl ContainerScreen {
+    const CELL_SIZE: f32 = 64.0;
+    const CELL_GAP: f32 = 6.0;
+    const COLUMNS: usize = 4;
+
+    fn slot_rects(container: &Container) -> Vec<Rect> {
+        let step = Self::CELL_SIZE + Self::CELL_GAP;
+        let top_left = vec2(
+            screen_width() - step * Self::COLUMNS as f32 - 25.0,
+            screen_height() / 2.0 - step * 2.0,
+        );
+        (0..container.items.len())
+            .map(|index| {
+                let (x, y) = (index % Self::COLUMNS, index / Self::COLUMNS);
+                Rect::new(
+                    top_left.x + step * x as f32,
+                    top_left.y + step * y as f32,
+                    Self::CELL_SIZE,
+                    Self::CELL_SIZE,
+                )
+            })
+            .collect()
+    }
+
+    // Returns the index of the item that was clicked
+    pub fn update(container: &Container) -> Option<usize> {
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return None;
+        }
+        let mouse_pos: Vec2 = mouse_position().into();
+        Self::slot_rects(container)
+            .iter()
+            .position(|rect| rect.contains(mouse_pos))
+    }
+
+    pub fn draw(container: &Container, assets: &Assets) {
+        let text_size = 20.0;
+        let rects = Self::slot_rects(container);
+        let title = match container.is_fully_searched() {
+            true => "Searched",
+            false => "Searching...",
+        };
+        if let Some(first) = rects.first() {
+            draw_text(title, first.x, first.y - 6.0, text_size * 1.3, WHITE);
+        } else {
+            draw_text(
+                "Empty",
+                screen_width() - 200.0,
+                screen_height() / 2.0,
+                text_size * 1.3,
+                WHITE,
+            );
+        }
+
+        let revealed = container.revealed();
+        for (index, rect) in rects.into_iter().enumerate() {
+            draw_rect(&rect, Color::new(0.15, 0.15, 0.15, 0.75));
+            draw_rect_lines(&rect, 2.0, BLACK);
+
+            if index < revealed {
+                if let Some(stack) = &container.items[index] {
+                    InventoryScreen::draw_stack(assets, stack, rect, text_size);
+                }
+            } else if index == revealed {
+                // Fill the cell being searched from the bottom up
+                let mut progress_rect = rect;
+                progress_rect.h *= container.search_progress();
+                progress_rect.y += rect.h - progress_rect.h;
+                draw_rect(&progress_rect, Color::new(1.0, 1.0, 1.0, 0.25));
+            }
+        }
+    }
+}
+
+pub const AUDIO_SETTINGS_KEY: KeyCode = KeyCode::Escape;
+
+// Panel for changing the volume of every bus, changes are saved to the audio settings file right away
+pub struct AudioSettingsScreen {
+    pub is_open: bool,
+}
+
+impl AudioSettingsScreen {
+    const WIDTH: f32 = 320.0;
+    const ROW_HEIGHT: f32 = 40.0;
+    const BUTTON_SIZE: f32 = 30.0;
+    const BUTTON_GAP: f32 = 6.0;
+    const VOLUME_STEP: f32 = 0.1;
+
+    pub fn new() -> Self {
+        Self { is_open: false }
+    }
+
+    // Volume down and volume up buttons of every bus
+    fn button_rects() -> Vec<(Bus, Rect, Rect)> {
+        let height = Self::ROW_HEIGHT * Bus::ALL.len() as f32;
+        let top_left = vec2(
+            (screen_width() - Self::WIDTH) / 2.0,
+            (screen_height() - height) / 2.0,
+        );
+        let right = top_left.x + Self::WIDTH;
+        Bus::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, bus)| {
+                let y = top_left.y + Self::ROW_HEIGHT * index as f32;
+                let up = Rect::new(
+                    right - Self::BUTTON_SIZE,
+                    y,
+                    Self::BUTTON_SIZE,
+                    Self::BUTTON_SIZE,
+                );
+                let down = up.offset(vec2(-Self::BUTTON_SIZE - Self::BUTTON_GAP, 0.0));
+                (*bus, down, up)
+            })
+            .collect()
+    }
+
+    pub fn update(&mut self, mixer: &Mixer) {
+        if is_key_pressed(AUDIO_SETTINGS_KEY) {
+            self.is_open = !self.is_open;
+        }
+        if !self.is_open || !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+        let mouse_pos: Vec2 = mouse_position().into();
+        for (bus, down, up) in Self::button_rects() {
+            let step = match (down.contains(mouse_pos), up.contains(mouse_pos)) {
+                (true, _) => -Self::VOLUME_STEP,
+                (_, true) => Self::VOLUME_STEP,
+                _ => continue,
+            };
+            // Rounded to the step so repeated clicks don't drift
+            let volume =
+                ((mixer.volume(bus) + step) / Self::VOLUME_STEP).round() * Self::VOLUME_STEP;
+            mixer.set_volume(bus, volume);
+            if let Err(err) = mixer.save_settings(AUDIO_SETTINGS_PATH) {
+                println!("{err}");
+            }
+        }
+    }
+
+    pub fn draw(&self, mixer: &Mixer) {
+        if !self.is_open {
+            return;
+        }
+        let text_size = 24.0;
+        let rects = Self::button_rects();
+        let (Some((_, first, _)), Some((_, last, _))) = (rects.first(), rects.last()) else {
+            return;
+        };
+        let left = (screen_width() - Self::WIDTH) / 2.0;
+        let panel = Rect::new(
+            left - 12.0,
+            first.y - 44.0,
+            Self::WIDTH + 24.0,
+            last.bottom() - first.y + 56.0,
+        );
+        draw_rect(&panel, Color::new(0.1, 0.1, 0.1, 0.85));
+        draw_rect_lines(&panel, 2.0, BLACK);
+        draw_text("Audio", left, first.y - 14.0, text_size * 1.3, WHITE);
+
+        for (bus, down, up) in rects {
+            draw_text(
+                &format!("{} {:.0}%", bus.name(), mixer.volume(bus) * 100.0),
+                left,
+                down.y + down.h * 0.7,
+                text_size,
+                WHITE,
+            );
+            for (rect, label) in [(down, "-"), (up, "+")] {
+                draw_rect(&rect, Color::new(0.25, 0.25, 0.25, 1.0));
+                draw_rect_lines(&rect, 2.0, BLACK);
+                draw_text(
+                    label,
+                    rect.x + 10.0,
+                    rect.y + rect.h * 0.7,
+                    text_size,
+                    WHITE,
+                );
+            }
+        }
+    }
+}
@@ -13,6 +13,17 @@ pub fn draw_rect(rect: Rect, color: Color) {
     draw_rectangle(rect.x, rect.y, rect.w, rect.h, color)
 }
 
+// Shortest distance from a point to the line segment between a and b
+pub fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let segment = b - a;
+    let length_squared = segment.length_squared();
+    if length_squared == 0.0 {
+        return point.distance(a);
+    }
+    let t = ((point - a).dot(segment) / length_squared).clamp(0.0, 1.0);
+    point.distance(a + segment * t)
+}
+
 pub fn conf() -> Conf {
     Conf {
         window_title: String::from("Top down shooter"),
@@ -27,3 +38,30 @@ pub fn conf() -> Conf {
         ..Default::default()
     }
 }
+
+// Small seedable random number generator (xorshift64*), used so the world can be
+// rolled independently of the global macroquad generator
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545F4914F6CDD1D) >> 32) as u32
+    }
+
+    // Random number in low..=high
+    pub fn gen_range_u32(&mut self, low: u32, high: u32) -> u32 {
+        if high <= low {
+            return low;
+        }
+        low + self.next_u32() % (high - low + 1)
+    }
+}
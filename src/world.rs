@@ -1,8 +1,22 @@
 use crate::{
-    assets::Assets, camera::GameCamera, entities::EntityManager, maps, player::Player,
+    assets::Assets,
+    camera::GameCamera,
+    doors::Door,
+    entities::EntityManager,
+    environment::Environment,
+    items::Item,
+    lighting::{Lamp, LightRenderer},
+    loot::{Container, ContainerKind, LootTable},
+    maps,
+    music::MusicPlayer,
+    noise::NoiseKind,
+    player::Player,
+    shadows::ShadowRenderer,
     tile_map::TileMap,
+    utils::Rng,
 };
 use macroquad::prelude::*;
+use std::collections::HashSet;
 
 pub const LINE_LENGTH: f32 = 23.0 * 8.0;
 pub const ANGLE_PERIPHERAL_FACTOR: f32 = 1.0;
@@ -15,29 +29,160 @@ pub const RAY_AMOUNT: f32 = 8.0;
 pub struct World {
     pub tile_map: TileMap,
     pub entities: EntityManager,
+    pub rng: Rng,
+    pub environment: Environment,
+    pub visible_tiles: HashSet<(u16, u16)>, // What the player sees this frame
+    music: MusicPlayer,
+    shadows: ShadowRenderer,
+    lighting: LightRenderer,
 }
 
 impl World {
     pub fn new() -> Self {
-        World {
+        let mut world = World {
             tile_map: maps::example_world(),
             entities: EntityManager::new(),
+            rng: Rng::new(miniquad::date::now().to_bits()),
+            environment: maps::example_world_environment(),
+            visible_tiles: HashSet::new(),
+            music: MusicPlayer::new(maps::example_world_ambience()),
+            shadows: ShadowRenderer::new(),
+            lighting: LightRenderer::new(),
+        };
+        world.spawn_containers(maps::example_world_containers());
+        world.spawn_doors(maps::example_world_doors());
+        world.spawn_lamps(maps::example_world_lamps());
+        world
+    }
+
+    fn spawn_doors(&mut self, doors: Vec<(u16, u16, Option<Item::Key>)>) {
+        for (grid_x, grid_y, key) in doors {
+            let door = Door::new(grid_x, grid_y, key, &mut self.tile_map);
+            self.entities.doors.push(door);
+        }
+    }
+
+    fn spawn_lamps(&mut self, lamps: Vec<(u16, u16, Color)>) {
+        for (grid_x, grid_y, color) in lamps {
+            self.entities.lamps.push(Lamp::new(grid_x, grid_y, color));
         }
     }
 
-    pub async fn update(&mut self, player: &Player, camera: &GameCamera, assets: &Assets) {
+    // Places containers and fills them by rolling their loot tables
+    fn spawn_containers(&mut self, containers: Vec<(u16, u16, ContainerKind)>) {
+        let crate_table = LootTable::load(ContainerKind::Crate.loot_table_path());
+        let locker_table = LootTable::load(ContainerKind::Locker.loot_table_path());
+
+        for (grid_x, grid_y, kind) in containers {
+            let table = match kind {
+                ContainerKind::Crate => &crate_table,
+                ContainerKind::Locker => &locker_table,
+            };
+            let items = table.roll(&mut self.rng);
+            self.entities
+                .containers
+                .push(Container::new(grid_x, grid_y, kind, items));
+        }
+    }
+
+    // Keys typed into the open debug console don't act in the game
+    pub async fn update(
+        &mut self,
+        player: &mut Player,
+        camera: &mut GameCamera,
+        assets: &Assets,
+        console_open: bool,
+    ) {
+        let weather_vision = self.environment.weather.vision_multiplier();
+        player.weather_vision = weather_vision;
+        for other_player in self.entities.other_players.iter_mut().flatten() {
+            other_player.weather_vision = weather_vision;
+        }
+        self.visible_tiles = player.visible_tiles(&self.tile_map);
+
         self.entities
-            .handle_shooting(assets, player, camera, &self.tile_map)
+            .handle_shooting(assets, player, camera, &mut self.tile_map, console_open)
             .await;
-        self.entities.update(player, camera);
+        self.entities
+            .handle_explosions(assets, player, camera, &mut self.tile_map);
+        self.entities.handle_interactions(
+            assets,
+            player,
+            &mut self.tile_map,
+            &self.visible_tiles,
+            console_open,
+        );
+        self.entities
+            .handle_player_collisions(player, &self.tile_map);
+        for noise in &self.entities.noises {
+            if matches!(noise.kind, NoiseKind::Gunshot | NoiseKind::Explosion) {
+                self.music
+                    .add_combat(noise.loudness_at(player.pos, &self.tile_map));
+            }
+        }
+        self.entities.handle_noises(player, &self.tile_map);
+        self.entities.update(player, camera, &self.tile_map);
+        self.environment.update(assets, camera);
+        let in_menu = player.inventory.is_open || self.entities.looting.is_some();
+        self.music.update(assets, in_menu, player.pos);
+
+        let enemies = self.entities.other_players.iter().enumerate();
+        player.memory.update(
+            &self.tile_map,
+            &self.visible_tiles,
+            enemies.filter_map(|(id, other)| Some((id, other.as_ref()?.pos))),
+        );
     }
 
     pub fn draw(&mut self, camera: &GameCamera, player: &Player, assets: &Assets) {
+        self.shadows
+            .update_mask(&self.tile_map, player, camera, &self.visible_tiles);
+        let lights = self.entities.lights(player);
+        let ambient = self.environment.ambient_light(self.tile_map.ambient_light);
+        self.lighting
+            .update_light_map(&self.tile_map, camera, ambient, &lights);
+        set_camera(camera);
+
         // Draws example world
-        self.tile_map.draw(assets, player, camera);
+        self.tile_map.draw(assets, camera, &player.memory);
+        self.entities.draw_fixtures();
+        self.shadows.draw_shadows(camera);
 
         // Draw entities
-        self.entities.draw_entities(assets, player, &self.tile_map);
+        self.shadows.begin_entities(camera);
+        self.entities.draw_entities(assets);
+        self.shadows.draw_clipped_entities(camera);
+        player.draw(assets);
+
+        self.lighting.draw_lighting(camera);
+        self.environment.draw();
+        set_default_camera();
+        self.environment.draw_fog();
+        set_camera(camera);
+
+        // Enemies that went out of sight are remembered where they were last seen
+        player.memory.draw_ghosts();
+    }
+
+    // Runs a command from the debug console, returning what to print back
+    pub fn run_command(&mut self, command: &str, assets: &Assets) -> String {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let result = match words[0] {
+            "help" => Ok(
+                "Commands: time <hours>, daylength <seconds>, weather <clear|rain|fog>, \
+                 volume [master|sfx|music|ui] [0-1], sounds, status"
+                    .to_string(),
+            ),
+            "volume" | "sounds" => assets.sounds.mixer.run_command(&words),
+            "status" => Ok(format!(
+                "Time {}, weather {}, music {}",
+                self.environment.clock(),
+                self.environment.weather.name(),
+                self.music.state.name()
+            )),
+            _ => self.environment.run_command(&words),
+        };
+        result.unwrap_or_else(|err| format!("Error: {err}"))
     }
 
     pub fn draw_debug(&self, camera: &GameCamera) {